@@ -1,54 +1,175 @@
+use std::collections::HashSet;
 use std::ops::RangeInclusive;
 
-fn parse_input(input: &str) -> Vec<RangeInclusive<i64>> {
+fn parse_input(input: &str, radix: u32) -> Result<Vec<RangeInclusive<i64>>, String> {
+    if !(2..=36).contains(&radix) {
+        return Err(format!("invalid radix {radix}: expected 2 to 36"));
+    }
+
     input
         .split(",")
         .map(|s| {
-            let (start, end) = s.split_once("-").unwrap();
-            start.parse().unwrap()..=end.parse().unwrap()
+            let s = s.trim();
+            if s.is_empty() {
+                return Err("empty range in input".to_string());
+            }
+
+            let (start, end) = s
+                .split_once("-")
+                .ok_or_else(|| format!("invalid range `{s}`: expected `start-end`"))?;
+            let start = i64::from_str_radix(start.trim(), radix)
+                .map_err(|e| format!("invalid range `{s}`: bad start: {e}"))?;
+            let end = i64::from_str_radix(end.trim(), radix)
+                .map_err(|e| format!("invalid range `{s}`: bad end: {e}"))?;
+
+            if start > end {
+                return Err(format!("invalid range `{s}`: start is greater than end"));
+            }
+
+            Ok(start..=end)
         })
         .collect()
 }
 
-fn solve1(input: &[RangeInclusive<i64>]) -> i64 {
-    let mut sum = 0;
+fn to_radix_string(mut num: i64, radix: u32) -> String {
+    assert!((2..=36).contains(&radix), "radix must be in 2..=36");
+    assert!(num >= 0, "ids must not be negative");
 
-    for range in input.iter().cloned() {
-        for i in range {
-            let id_str = i.to_string();
-            if id_str.len() % 2 == 0 {
-                let (left, right) = id_str.split_at(id_str.len() / 2);
-                if left == right {
-                    sum += i;
-                }
-            }
-        }
+    if num == 0 {
+        return "0".to_string();
+    }
+
+    let mut digits = Vec::new();
+    while num > 0 {
+        digits.push(char::from_digit((num % radix as i64) as u32, radix).unwrap());
+        num /= radix as i64;
     }
 
-    sum
+    digits.iter().rev().collect()
 }
 
-fn solve2(input: &[RangeInclusive<i64>]) -> i64 {
-    let mut sum = 0;
-    let invalid_id_regex = fancy_regex::Regex::new(r"^([0-9]+?)\1+$").unwrap();
+#[derive(Debug, Clone, Copy)]
+enum Rule {
+    /// id is some block of digits repeated exactly twice
+    Doubled,
+    /// id is some block of digits repeated at least twice
+    Repeated,
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    /// only count an invalid id for the first range that contains it
+    dedup_overlaps: bool,
+}
+
+#[derive(Debug)]
+struct RangeReport {
+    range: RangeInclusive<i64>,
+    invalid_ids: Vec<i64>,
+    subtotal: i64,
+}
+
+fn report(
+    input: &[RangeInclusive<i64>],
+    radix: u32,
+    rule: Rule,
+    options: &Options,
+) -> Vec<RangeReport> {
+    let invalid_id_regex = fancy_regex::Regex::new(r"^([0-9a-z]+?)\1+$").unwrap();
+    let is_invalid = |id_str: &str| match rule {
+        Rule::Doubled => {
+            if id_str.len().is_multiple_of(2) {
+                let (left, right) = id_str.split_at(id_str.len() / 2);
+                left == right
+            } else {
+                false
+            }
+        }
+        Rule::Repeated => invalid_id_regex.is_match(id_str).unwrap(),
+    };
+
+    let mut seen = HashSet::new();
+    let mut reports = Vec::new();
 
     for range in input.iter().cloned() {
-        for i in range {
-            let id_str = i.to_string();
-            if invalid_id_regex.is_match(&id_str).unwrap() {
-                sum += i;
+        let mut invalid_ids = Vec::new();
+        for i in range.clone() {
+            if is_invalid(&to_radix_string(i, radix)) && (!options.dedup_overlaps || seen.insert(i))
+            {
+                invalid_ids.push(i);
             }
         }
+
+        reports.push(RangeReport {
+            range,
+            subtotal: invalid_ids.iter().sum(),
+            invalid_ids,
+        });
+    }
+
+    reports
+}
+
+fn print_report(reports: &[RangeReport], radix: u32) {
+    for report in reports {
+        let ids: Vec<String> = report
+            .invalid_ids
+            .iter()
+            .map(|&i| to_radix_string(i, radix))
+            .collect();
+        println!(
+            "{}-{}: [{}] subtotal {}",
+            to_radix_string(*report.range.start(), radix),
+            to_radix_string(*report.range.end(), radix),
+            ids.join(", "),
+            report.subtotal
+        );
     }
+}
 
-    sum
+fn solve1(input: &[RangeInclusive<i64>], radix: u32, options: &Options) -> i64 {
+    report(input, radix, Rule::Doubled, options)
+        .iter()
+        .map(|r| r.subtotal)
+        .sum()
+}
+
+fn solve2(input: &[RangeInclusive<i64>], radix: u32, options: &Options) -> i64 {
+    report(input, radix, Rule::Repeated, options)
+        .iter()
+        .map(|r| r.subtotal)
+        .sum()
 }
 
 fn main() {
-    let parsed = parse_input(include_str!("input.txt"));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = Options {
+        dedup_overlaps: args.iter().any(|a| a == "--dedup"),
+    };
+    let radix = args
+        .iter()
+        .find_map(|a| a.strip_prefix("--radix="))
+        .map_or(10, |radix| {
+            radix.parse().unwrap_or_else(|e| {
+                eprintln!("invalid radix `{radix}`: {e}");
+                std::process::exit(1);
+            })
+        });
+
+    let parsed = parse_input(include_str!("input.txt"), radix).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
 
-    println!("Part 1: {}", solve1(&parsed));
-    println!("Part 2: {}", solve2(&parsed));
+    if args.iter().any(|a| a == "--report") {
+        println!("Part 1:");
+        print_report(&report(&parsed, radix, Rule::Doubled, &options), radix);
+        println!("Part 2:");
+        print_report(&report(&parsed, radix, Rule::Repeated, &options), radix);
+    }
+
+    println!("Part 1: {}", solve1(&parsed, radix, &options));
+    println!("Part 2: {}", solve2(&parsed, radix, &options));
 }
 
 #[cfg(test)]
@@ -59,13 +180,73 @@ mod tests {
 
     #[test]
     fn test1() {
-        let parsed = parse_input(INPUT);
-        assert_eq!(solve1(&parsed), 1227775554);
+        let parsed = parse_input(INPUT, 10).unwrap();
+        assert_eq!(solve1(&parsed, 10, &Options::default()), 1227775554);
     }
 
     #[test]
     fn test2() {
-        let parsed = parse_input(INPUT);
-        assert_eq!(solve2(&parsed), 4174379265);
+        let parsed = parse_input(INPUT, 10).unwrap();
+        assert_eq!(solve2(&parsed, 10, &Options::default()), 4174379265);
+    }
+
+    #[test]
+    fn test_hex() {
+        // 0xaa and 0xbb are doubled, 0xaaa is only repeated
+        let parsed = parse_input("a0-BF,aa9-aab", 16).unwrap();
+        assert_eq!(solve1(&parsed, 16, &Options::default()), 0xaa + 0xbb);
+        assert_eq!(
+            solve2(&parsed, 16, &Options::default()),
+            0xaa + 0xbb + 0xaaa
+        );
+    }
+
+    #[test]
+    fn test_binary() {
+        // 0b1010, 0b1111 are doubled, 0b111 is only repeated
+        let parsed = parse_input("111-1111", 2).unwrap();
+        assert_eq!(solve1(&parsed, 2, &Options::default()), 0b1010 + 0b1111);
+        assert_eq!(
+            solve2(&parsed, 2, &Options::default()),
+            0b111 + 0b1010 + 0b1111
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let parsed = parse_input("11-22, 95-115", 10).unwrap();
+        let reports = report(&parsed, 10, Rule::Doubled, &Options::default());
+        assert_eq!(reports[0].invalid_ids, vec![11, 22]);
+        assert_eq!(reports[0].subtotal, 33);
+        assert_eq!(reports[1].invalid_ids, vec![99]);
+        assert_eq!(reports[1].subtotal, 99);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let parsed = parse_input("10-30,20-40", 10).unwrap();
+        assert_eq!(solve1(&parsed, 10, &Options::default()), 11 + 22 + 22 + 33);
+
+        let options = Options {
+            dedup_overlaps: true,
+        };
+        let reports = report(&parsed, 10, Rule::Doubled, &options);
+        assert_eq!(reports[0].invalid_ids, vec![11, 22]);
+        assert_eq!(reports[1].invalid_ids, vec![33]);
+        assert_eq!(solve1(&parsed, 10, &options), 11 + 22 + 33);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(
+            parse_input("5-3", 10)
+                .unwrap_err()
+                .contains("start is greater than end")
+        );
+        assert!(parse_input("1-2,,3-4", 10).is_err());
+        assert!(parse_input("12", 10).is_err());
+        assert!(parse_input("1-x", 10).is_err());
+        assert!(parse_input("1-2", 1).unwrap_err().contains("invalid radix"));
+        assert!(parse_input("1-2", 37).is_err());
     }
 }
@@ -1,69 +1,218 @@
+use std::collections::VecDeque;
+
+use num_bigint::BigUint;
+
 fn parse_input<'a>(input: impl Iterator<Item = &'a str>) -> Vec<Vec<u32>> {
     input
         .map(|line| line.chars().map(|c| c.to_digit(10).unwrap()).collect())
         .collect()
 }
 
-fn solve1(input: &[Vec<u32>]) -> u32 {
-    let mut sum = 0;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TieBreak {
+    /// among equally good selections, prefer the earliest batteries
+    Leftmost,
+    /// among equally good selections, prefer the latest batteries
+    Rightmost,
+}
 
-    for bank in input {
-        let mut max_val = 0;
-        for i in 0..bank.len() - 1 {
-            for j in i + 1..bank.len() {
-                let val = bank[i] * 10 + bank[j];
-                max_val = max_val.max(val);
-            }
-        }
-        sum += max_val;
-    }
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Objective {
+    #[default]
+    Max,
+    Min,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Rules {
+    objective: Objective,
+    /// no two selected batteries may be next to each other
+    no_adjacent: bool,
+    /// digits that may never be selected
+    forbidden: Vec<u32>,
+}
 
-    sum
+#[derive(Debug, PartialEq, Eq)]
+struct Selection {
+    indices: Vec<usize>,
+    joltage: BigUint,
 }
 
-fn solve2(input: &[Vec<u32>]) -> u64 {
-    fn maximize_bank(bank: &[u32], cur_val: u64, cur_digits: usize, best: &mut u64) {
-        if bank.len() + cur_digits < 12 {
-            return;
+fn select_batteries(
+    bank: &[u32],
+    k: usize,
+    rules: &Rules,
+    tie_break: TieBreak,
+) -> Option<Selection> {
+    let n = bank.len();
+    let gap = if rules.no_adjacent { 2 } else { 1 };
+    let allowed: Vec<bool> = bank
+        .iter()
+        .map(|digit| !rules.forbidden.contains(digit))
+        .collect();
+
+    // capacity[i] is the most batteries that can still be selected from bank[i..]
+    let mut capacity = vec![0; n + gap];
+    for i in (0..n).rev() {
+        capacity[i] = if allowed[i] {
+            1 + capacity[i + gap]
+        } else {
+            capacity[i + 1]
+        };
+    }
+    if capacity[0] < k {
+        return None;
+    }
+
+    let better = |a: u32, b: u32| match rules.objective {
+        Objective::Max => a > b,
+        Objective::Min => a < b,
+    };
+
+    // greedily pick the best digit from a sliding window of positions that still
+    // leave room for the remaining picks. the window is a monotonic deque whose
+    // front is the leftmost best candidate, so every battery is pushed and popped
+    // at most once
+    let mut window = VecDeque::new();
+    let mut next = 0;
+    let mut indices = Vec::with_capacity(k);
+    for remaining in (0..k).rev() {
+        while next < n {
+            if allowed[next] {
+                if capacity[next + gap] < remaining {
+                    break;
+                }
+                while window
+                    .back()
+                    .is_some_and(|&last: &usize| better(bank[next], bank[last]))
+                {
+                    window.pop_back();
+                }
+                window.push_back(next);
+            }
+            next += 1;
         }
 
-        if cur_digits == 12 {
-            *best = (*best).max(cur_val);
-            return;
+        let pick = window.pop_front().unwrap();
+        while window.front().is_some_and(|&i| i < pick + gap) {
+            window.pop_front();
         }
+        next = next.max(pick + gap);
+        indices.push(pick);
+    }
 
-        // early exit if we can't do better than our best
-        let mut max_achievable = cur_val;
-        for _ in cur_digits..12 {
-            max_achievable = max_achievable * 10 + 9;
+    if tie_break == TieBreak::Rightmost {
+        // the greedy gives the leftmost embedding of the chosen digits, re-embed the
+        // same digits scanning from the right to get the rightmost one
+        let mut limit = n;
+        for slot in indices.iter_mut().rev() {
+            let digit = bank[*slot];
+            let pos = bank[..limit].iter().rposition(|&d| d == digit).unwrap();
+            *slot = pos;
+            limit = (pos + 1).saturating_sub(gap);
         }
+    }
+
+    // digits are accumulated into a big integer so k is only bounded by the bank length
+    let joltage = indices
+        .iter()
+        .fold(BigUint::ZERO, |acc, &i| acc * 10u32 + bank[i]);
 
-        if max_achievable >= *best {
-            maximize_bank(
-                &bank[1..],
-                cur_val * 10 + (bank[0] as u64),
-                cur_digits + 1,
-                best,
-            );
-            maximize_bank(&bank[1..], cur_val, cur_digits, best);
+    Some(Selection { indices, joltage })
+}
+
+fn joltage(bank: &[u32], k: usize, rules: &Rules) -> Option<BigUint> {
+    select_batteries(bank, k, rules, TieBreak::Leftmost).map(|selection| selection.joltage)
+}
+
+fn render_bank(bank: &[u32], selection: &Selection) -> String {
+    let mut selected = selection.indices.iter().peekable();
+    let mut rendered = String::new();
+    for (i, digit) in bank.iter().enumerate() {
+        if selected.next_if_eq(&&i).is_some() {
+            rendered.push_str(&format!("\x1b[1;32m{digit}\x1b[0m"));
+        } else {
+            rendered.push_str(&digit.to_string());
         }
     }
 
-    let mut sum = 0;
+    rendered
+}
+
+fn total_joltage(input: &[Vec<u32>], k: usize, rules: &Rules) -> Result<BigUint, String> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, bank)| {
+            joltage(bank, k, rules)
+                .ok_or_else(|| format!("bank {} has no valid selection of {k} batteries", i + 1))
+        })
+        .sum()
+}
+
+fn solve1(input: &[Vec<u32>], rules: &Rules) -> Result<BigUint, String> {
+    total_joltage(input, 2, rules)
+}
+
+fn solve2(input: &[Vec<u32>], rules: &Rules) -> Result<BigUint, String> {
+    total_joltage(input, 12, rules)
+}
+
+fn print_report(input: &[Vec<u32>], k: usize, rules: &Rules, tie_break: TieBreak) {
     for bank in input {
-        let mut best = 0;
-        maximize_bank(bank, 0, 0, &mut best);
-        sum += best;
+        match select_batteries(bank, k, rules, tie_break) {
+            Some(selection) => println!(
+                "{} {:?} {}",
+                render_bank(bank, &selection),
+                selection.indices,
+                selection.joltage
+            ),
+            None => {
+                let digits: String = bank.iter().map(|d| d.to_string()).collect();
+                println!("{digits} no valid selection");
+            }
+        }
     }
-
-    sum
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let tie_break = if args.iter().any(|a| a == "--rightmost") {
+        TieBreak::Rightmost
+    } else {
+        TieBreak::Leftmost
+    };
+    let rules = Rules {
+        objective: if args.iter().any(|a| a == "--min") {
+            Objective::Min
+        } else {
+            Objective::Max
+        },
+        no_adjacent: args.iter().any(|a| a == "--no-adjacent"),
+        forbidden: args
+            .iter()
+            .filter_map(|a| a.strip_prefix("--forbid="))
+            .flat_map(|digits| digits.chars().map(|c| c.to_digit(10).unwrap()))
+            .collect(),
+    };
+
     let parsed = parse_input(include_str!("input.txt").lines());
 
-    println!("Part 1: {}", solve1(&parsed));
-    println!("Part 2: {}", solve2(&parsed));
+    if args.iter().any(|a| a == "--report") {
+        println!("Part 1:");
+        print_report(&parsed, 2, &rules, tie_break);
+        println!("Part 2:");
+        print_report(&parsed, 12, &rules, tie_break);
+    }
+
+    match solve1(&parsed, &rules) {
+        Ok(total) => println!("Part 1: {total}"),
+        Err(e) => println!("Part 1: {e}"),
+    }
+    match solve2(&parsed, &rules) {
+        Ok(total) => println!("Part 2: {total}"),
+        Err(e) => println!("Part 2: {e}"),
+    }
 }
 
 #[cfg(test)]
@@ -80,12 +229,154 @@ mod tests {
     #[test]
     fn test1() {
         let parsed = parse_input(INPUT.iter().cloned());
-        assert_eq!(solve1(&parsed), 357);
+        assert_eq!(solve1(&parsed, &Rules::default()).unwrap(), 357u32.into());
     }
 
     #[test]
     fn test2() {
         let parsed = parse_input(INPUT.iter().cloned());
-        assert_eq!(solve2(&parsed), 3121910778619);
+        assert_eq!(
+            solve2(&parsed, &Rules::default()).unwrap(),
+            3121910778619u64.into()
+        );
+    }
+
+    #[test]
+    fn test_joltage() {
+        let parsed = parse_input(INPUT.iter().cloned());
+        assert_eq!(
+            joltage(&parsed[0], 1, &Rules::default()).unwrap(),
+            9u32.into()
+        );
+        assert_eq!(
+            joltage(&parsed[1], 2, &Rules::default()).unwrap(),
+            89u32.into()
+        );
+        assert_eq!(
+            joltage(&parsed[2], 15, &Rules::default()).unwrap(),
+            234234234234278u64.into()
+        );
+    }
+
+    #[test]
+    fn test_large_bank() {
+        let bank: Vec<u32> = (0..100_000).map(|i| (i * 7 % 10) as u32).collect();
+        assert_eq!(
+            joltage(&bank, 12, &Rules::default()).unwrap(),
+            999999999999u64.into()
+        );
+    }
+
+    #[test]
+    fn test_selection() {
+        let parsed = parse_input(INPUT.iter().cloned());
+        let selection =
+            select_batteries(&parsed[1], 2, &Rules::default(), TieBreak::Leftmost).unwrap();
+        assert_eq!(selection.indices, vec![0, 14]);
+        assert_eq!(selection.joltage, 89u32.into());
+
+        let bank = parse_input(["9919"].into_iter()).remove(0);
+        let leftmost = select_batteries(&bank, 2, &Rules::default(), TieBreak::Leftmost).unwrap();
+        let rightmost = select_batteries(&bank, 2, &Rules::default(), TieBreak::Rightmost).unwrap();
+        assert_eq!(leftmost.indices, vec![0, 1]);
+        assert_eq!(rightmost.indices, vec![1, 3]);
+        assert_eq!(leftmost.joltage, rightmost.joltage);
+
+        assert_eq!(
+            render_bank(&bank, &rightmost),
+            "9\x1b[1;32m9\x1b[0m1\x1b[1;32m9\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_wide_joltage() {
+        let bank: Vec<u32> = "9"
+            .repeat(40)
+            .chars()
+            .map(|c| c.to_digit(10).unwrap())
+            .collect();
+        let expected: BigUint = "9".repeat(30).parse().unwrap();
+        assert_eq!(joltage(&bank, 30, &Rules::default()).unwrap(), expected);
+        assert_eq!(
+            joltage(&bank, 40, &Rules::default()).unwrap(),
+            "9".repeat(40).parse().unwrap()
+        );
+
+        // 20 nines overflow u64 on their own, summing several must stay exact
+        let banks = vec![bank.clone(); 5];
+        let total: BigUint = banks
+            .iter()
+            .map(|b| joltage(b, 20, &Rules::default()).unwrap())
+            .sum();
+        let expected: BigUint = format!("4{}5", "9".repeat(19)).parse().unwrap();
+        assert_eq!(total, expected);
+    }
+
+    #[test]
+    fn test_min_joltage() {
+        let rules = Rules {
+            objective: Objective::Min,
+            ..Default::default()
+        };
+        let parsed = parse_input(INPUT.iter().cloned());
+        assert_eq!(joltage(&parsed[0], 2, &rules).unwrap(), 11u32.into());
+        assert_eq!(joltage(&parsed[2], 3, &rules).unwrap(), 222u32.into());
+        assert_eq!(joltage(&parsed[3], 4, &rules).unwrap(), 1111u32.into());
+    }
+
+    #[test]
+    fn test_no_adjacent() {
+        let rules = Rules {
+            no_adjacent: true,
+            ..Default::default()
+        };
+        let bank = parse_input(["19981"].into_iter()).remove(0);
+        let selection = select_batteries(&bank, 2, &rules, TieBreak::Leftmost).unwrap();
+        assert_eq!(selection.indices, vec![1, 3]);
+        assert_eq!(selection.joltage, 98u32.into());
+        assert_eq!(joltage(&bank, 3, &rules).unwrap(), 191u32.into());
+        assert!(select_batteries(&bank, 4, &rules, TieBreak::Leftmost).is_none());
+
+        let bank = parse_input(["90909"].into_iter()).remove(0);
+        let leftmost = select_batteries(&bank, 2, &rules, TieBreak::Leftmost).unwrap();
+        let rightmost = select_batteries(&bank, 2, &rules, TieBreak::Rightmost).unwrap();
+        assert_eq!(leftmost.indices, vec![0, 2]);
+        assert_eq!(rightmost.indices, vec![2, 4]);
+        assert_eq!(rightmost.joltage, 99u32.into());
+    }
+
+    #[test]
+    fn test_forbidden() {
+        let rules = Rules {
+            forbidden: vec![9, 8],
+            ..Default::default()
+        };
+        let parsed = parse_input(INPUT.iter().cloned());
+        assert_eq!(joltage(&parsed[0], 2, &rules).unwrap(), 76u32.into());
+        assert_eq!(joltage(&parsed[2], 3, &rules).unwrap(), 447u32.into());
+        assert_eq!(joltage(&parsed[1], 2, &rules).unwrap(), 11u32.into());
+
+        let bank = parse_input(["98989"].into_iter()).remove(0);
+        assert!(select_batteries(&bank, 1, &rules, TieBreak::Leftmost).is_none());
+    }
+
+    #[test]
+    fn test_infeasible() {
+        let rules = Rules {
+            forbidden: (1..=9).collect(),
+            ..Default::default()
+        };
+        let parsed = parse_input(INPUT.iter().cloned());
+        assert_eq!(
+            solve1(&parsed, &rules).unwrap_err(),
+            "bank 1 has no valid selection of 2 batteries"
+        );
+
+        let rules = Rules {
+            no_adjacent: true,
+            ..Default::default()
+        };
+        assert!(solve1(&parsed, &rules).is_ok());
+        assert!(solve2(&parsed, &rules).is_err());
     }
 }
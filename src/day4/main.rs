@@ -1,62 +1,237 @@
 use std::ops::Deref;
 
-#[derive(Debug, Clone, Copy)]
-enum Space {
-    Empty,
-    Paper,
+/// index of the cell's material in a `Materials` registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Space(u8);
+
+impl Space {
+    const EMPTY: Space = Space(0);
+}
+
+#[derive(Debug, Clone)]
+struct Material {
+    symbol: char,
+    /// rolls of this material can be taken out once accessible
+    removable: bool,
+    /// cells of this material crowd the rolls around them
+    counts_as_neighbor: bool,
 }
 
-fn parse_input<'a>(input: impl Iterator<Item = &'a str>) -> Vec<Vec<Space>> {
+#[derive(Debug, Clone)]
+struct Materials(Vec<Material>);
+
+impl Default for Materials {
+    /// empty floor and paper rolls, as in the puzzle
+    fn default() -> Self {
+        Self(vec![
+            Material {
+                symbol: '.',
+                removable: false,
+                counts_as_neighbor: false,
+            },
+            Material {
+                symbol: '@',
+                removable: true,
+                counts_as_neighbor: true,
+            },
+        ])
+    }
+}
+
+impl Materials {
+    fn register(&mut self, material: Material) -> Space {
+        assert!(
+            self.0.iter().all(|m| m.symbol != material.symbol),
+            "Material {} already registered",
+            material.symbol
+        );
+        assert!(self.0.len() <= u8::MAX as usize, "Too many materials");
+
+        self.0.push(material);
+        Space((self.0.len() - 1) as u8)
+    }
+
+    fn get(&self, space: Space) -> &Material {
+        &self.0[space.0 as usize]
+    }
+
+    fn parse(&self, c: char) -> Option<Space> {
+        self.0
+            .iter()
+            .position(|m| m.symbol == c)
+            .map(|i| Space(i as u8))
+    }
+}
+
+fn parse_input<'a>(
+    input: impl Iterator<Item = &'a str>,
+    materials: &Materials,
+) -> Result<Vec<Vec<Space>>, String> {
     input
-        .map(|line| {
+        .enumerate()
+        .map(|(y, line)| {
             line.chars()
-                .map(|c| match c {
-                    '.' => Space::Empty,
-                    '@' => Space::Paper,
-                    _ => panic!("Invalid char {c}"),
+                .enumerate()
+                .map(|(x, c)| {
+                    materials.parse(c).ok_or_else(|| {
+                        format!("unknown material {c:?} at line {}, column {}", y + 1, x + 1)
+                    })
                 })
                 .collect()
         })
         .collect()
 }
 
-fn grid_8_neighbors<'a, T: 'a, S>(grid: &'a [S], x: usize, y: usize) -> impl Iterator<Item = &'a T>
+const OFFSETS_4: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const OFFSETS_8: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+// hex cells in "odd-r" layout, where odd rows are shifted half a cell to the right
+const OFFSETS_HEX_EVEN: [(isize, isize); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+const OFFSETS_HEX_ODD: [(isize, isize); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+
+#[derive(Debug, Clone)]
+enum Neighborhood {
+    Four,
+    Eight,
+    /// see `OFFSETS_HEX_EVEN`
+    Hex,
+    /// arbitrary `(x, y)` offsets
+    Custom(Vec<(isize, isize)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edges {
+    /// cells past the edge of the grid don't exist
+    Bounded,
+    /// the grid wraps around like a torus
+    Wrap,
+}
+
+#[derive(Debug, Clone)]
+struct AccessRule {
+    neighborhood: Neighborhood,
+    /// a roll is accessible when fewer than this many of its neighbours are of a
+    /// material that counts as a neighbour
+    threshold: usize,
+    edges: Edges,
+}
+
+impl Default for AccessRule {
+    /// the rule from the puzzle
+    fn default() -> Self {
+        Self {
+            neighborhood: Neighborhood::Eight,
+            threshold: 4,
+            edges: Edges::Bounded,
+        }
+    }
+}
+
+impl AccessRule {
+    /// the rule whose neighbourhood of a cell is every cell that has it as a neighbour
+    fn reversed(&self) -> Self {
+        let neighborhood = match &self.neighborhood {
+            Neighborhood::Custom(offsets) => {
+                Neighborhood::Custom(offsets.iter().map(|&(x, y)| (-x, -y)).collect())
+            }
+            // the built in neighbourhoods are symmetric
+            neighborhood => neighborhood.clone(),
+        };
+
+        Self {
+            neighborhood,
+            ..self.clone()
+        }
+    }
+
+    /// whether `reversed` gives exactly the cells that have a cell as a neighbour, which
+    /// stops holding once wrapping meets ragged rows or an odd number of hex rows
+    fn is_reversible(&self, grid: &[Vec<Space>]) -> bool {
+        match self.edges {
+            Edges::Bounded => true,
+            Edges::Wrap => {
+                let odd_hex =
+                    matches!(self.neighborhood, Neighborhood::Hex) && !grid.len().is_multiple_of(2);
+                !odd_hex && grid.windows(2).all(|rows| rows[0].len() == rows[1].len())
+            }
+        }
+    }
+
+    fn offsets(&self, y: usize) -> &[(isize, isize)] {
+        match &self.neighborhood {
+            Neighborhood::Four => &OFFSETS_4,
+            Neighborhood::Eight => &OFFSETS_8,
+            Neighborhood::Hex if y.is_multiple_of(2) => &OFFSETS_HEX_EVEN,
+            Neighborhood::Hex => &OFFSETS_HEX_ODD,
+            Neighborhood::Custom(offsets) => offsets,
+        }
+    }
+}
+
+fn neighbor_positions<'a, T, S>(
+    grid: &'a [S],
+    x: usize,
+    y: usize,
+    rule: &'a AccessRule,
+) -> impl Iterator<Item = (usize, usize)> + 'a
 where
     S: Deref<Target = [T]>,
 {
-    (-1..=1)
-        .filter_map(move |y_diff| {
-            y.checked_add_signed(y_diff)
-                .filter(|&new_y| new_y < grid.len())
-        })
-        .flat_map(move |new_y| {
-            (-1..=1).filter_map(move |x_diff| {
-                if let Some(new_x) = x.checked_add_signed(x_diff) {
-                    if new_x < grid[new_y].len() && !(new_x == x && new_y == y) {
-                        Some(&grid[new_y][new_x])
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-        })
+    let height = grid.len();
+    let wrap = rule.edges == Edges::Wrap;
+    rule.offsets(y).iter().filter_map(move |&(x_diff, y_diff)| {
+        if !wrap {
+            let new_x = x.checked_add_signed(x_diff)?;
+            let new_y = y.checked_add_signed(y_diff)?;
+            return (new_y < height && new_x < grid[new_y].len() && (x_diff, y_diff) != (0, 0))
+                .then_some((new_x, new_y));
+        }
+
+        let new_y = (y as isize + y_diff).rem_euclid(height as isize) as usize;
+        let width = grid[new_y].len();
+        if width == 0 {
+            return None;
+        }
+        let new_x = (x as isize + x_diff).rem_euclid(width as isize) as usize;
+
+        // small wrapped grids can lead back to the cell itself
+        (new_x, new_y).ne(&(x, y)).then_some((new_x, new_y))
+    })
 }
 
-fn solve1(input: &[Vec<Space>]) -> i64 {
+fn neighbors<'a, T: 'a, S>(
+    grid: &'a [S],
+    x: usize,
+    y: usize,
+    rule: &'a AccessRule,
+) -> impl Iterator<Item = &'a T>
+where
+    S: Deref<Target = [T]>,
+{
+    neighbor_positions(grid, x, y, rule).map(move |(new_x, new_y)| &grid[new_y][new_x])
+}
+
+fn solve1(input: &[Vec<Space>], rule: &AccessRule, materials: &Materials) -> i64 {
     let mut total = 0;
 
     for (y, row) in input.iter().enumerate() {
         for (x, _col) in row
             .iter()
             .enumerate()
-            .filter(|c| matches!(c.1, Space::Paper))
+            .filter(|c| materials.get(*c.1).removable)
         {
-            if grid_8_neighbors(input, x, y)
-                .filter(|neighbor| matches!(**neighbor, Space::Paper))
+            if neighbors(input, x, y, rule)
+                .filter(|neighbor| materials.get(**neighbor).counts_as_neighbor)
                 .count()
-                < 4
+                < rule.threshold
             {
                 total += 1;
             }
@@ -66,51 +241,324 @@ fn solve1(input: &[Vec<Space>]) -> i64 {
     total
 }
 
-fn solve2(input: &[Vec<Space>]) -> i64 {
-    let mut cur_grid: Vec<Vec<Space>> = Vec::from_iter(input.iter().cloned());
-    let mut total = 0;
+/// the removable rolls that count each cell as one of their neighbours, once per time
+/// they count it
+fn crowded_by(
+    input: &[Vec<Space>],
+    rule: &AccessRule,
+    materials: &Materials,
+) -> Vec<Vec<Vec<(usize, usize)>>> {
+    let mut crowded_by: Vec<Vec<Vec<(usize, usize)>>> = input
+        .iter()
+        .map(|row| vec![Vec::new(); row.len()])
+        .collect();
+    for (y, row) in input.iter().enumerate() {
+        for (x, col) in row.iter().enumerate() {
+            if materials.get(*col).removable {
+                for (n_x, n_y) in neighbor_positions(input, x, y, rule) {
+                    crowded_by[n_y][n_x].push((x, y));
+                }
+            }
+        }
+    }
 
-    loop {
-        let mut round_total = 0;
-        let mut new_grid = Vec::new();
-        for (y, row) in cur_grid.iter().enumerate() {
-            let mut new_row = Vec::new();
-            for (x, col) in row.iter().enumerate() {
-                match col {
-                    Space::Empty => new_row.push(Space::Empty),
-                    Space::Paper => {
-                        if grid_8_neighbors(&cur_grid, x, y)
-                            .filter(|neighbor| matches!(**neighbor, Space::Paper))
-                            .count()
-                            < 4
-                        {
-                            round_total += 1;
-                            new_row.push(Space::Empty);
-                        } else {
-                            new_row.push(Space::Paper)
+    crowded_by
+}
+
+#[derive(Debug)]
+struct Erosion {
+    /// round (starting at 1) in which each cell's roll is removed, `None` for empty
+    /// cells and rolls that are never removed
+    removal_rounds: Vec<Vec<Option<u32>>>,
+    final_grid: Vec<Vec<Space>>,
+}
+
+/// removes accessible rolls round by round until none are left, calling `removed` with
+/// the position of every roll taken out and its round, starting at 1
+fn erode_with(
+    input: &[Vec<Space>],
+    rule: &AccessRule,
+    materials: &Materials,
+    mut removed: impl FnMut(usize, usize, u32),
+) {
+    // material flags looked up once, rather than for every cell
+    let removable: Vec<bool> = materials.0.iter().map(|m| m.removable).collect();
+    let crowds: Vec<bool> = materials.0.iter().map(|m| m.counts_as_neighbor).collect();
+    let crowding: Vec<Vec<u16>> = input
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|col| crowds[col.0 as usize] as u16)
+                .collect()
+        })
+        .collect();
+
+    // cells are numbered row by row, so counts fit in a single vec even for ragged rows
+    let row_starts: Vec<usize> = std::iter::once(0)
+        .chain(input.iter().scan(0, |end, row| {
+            *end += row.len();
+            Some(*end)
+        }))
+        .collect();
+
+    // neighbour count of every roll still waiting to be removed, kept up to date as
+    // rolls are removed. every other cell is settled, which also stops a roll being
+    // queued twice
+    const SETTLED: u16 = u16::MAX;
+    let mut neighbor_counts = vec![0; row_starts[input.len()]];
+    let height = input.len();
+    let mut wave = Vec::new();
+    for (y, row) in input.iter().enumerate() {
+        let counts = &mut neighbor_counts[row_starts[y]..row_starts[y + 1]];
+
+        // counts are gathered one offset at a time along the whole row, which keeps
+        // the bounded case a plain walk over two slices
+        for &(x_diff, y_diff) in rule.offsets(y) {
+            if (x_diff, y_diff) == (0, 0) {
+                continue;
+            }
+            match rule.edges {
+                Edges::Bounded => {
+                    let Some(neighbor_row) = y
+                        .checked_add_signed(y_diff)
+                        .and_then(|n_y| crowding.get(n_y))
+                    else {
+                        continue;
+                    };
+                    // cells from `start` to `end` have their neighbour within the row
+                    let start = x_diff.min(0).unsigned_abs();
+                    let end = row
+                        .len()
+                        .min((neighbor_row.len() as isize - x_diff).max(0) as usize);
+                    if start >= end {
+                        continue;
+                    }
+                    let shifted =
+                        (start as isize + x_diff) as usize..(end as isize + x_diff) as usize;
+                    for (count, neighbor) in
+                        counts[start..end].iter_mut().zip(&neighbor_row[shifted])
+                    {
+                        *count += neighbor;
+                    }
+                }
+                Edges::Wrap => {
+                    let n_y = (y as isize + y_diff).rem_euclid(height as isize) as usize;
+                    let width = crowding[n_y].len();
+                    if width == 0 {
+                        continue;
+                    }
+                    for (x, count) in counts.iter_mut().enumerate() {
+                        let n_x = (x as isize + x_diff).rem_euclid(width as isize) as usize;
+                        // small wrapped grids can lead back to the cell itself
+                        if (n_x, n_y) != (x, y) {
+                            *count += crowding[n_y][n_x];
                         }
                     }
                 }
             }
-            new_grid.push(new_row);
         }
 
-        if round_total == 0 {
-            break;
-        } else {
-            total += round_total;
-            cur_grid = new_grid;
+        for (x, (col, count)) in row.iter().zip(counts).enumerate() {
+            if !removable[col.0 as usize] {
+                *count = SETTLED;
+            } else if (*count as usize) < rule.threshold {
+                *count = SETTLED;
+                wave.push((x, y));
+            }
+        }
+    }
+
+    // every roll accessible at the start of a round is removed in that round, only
+    // the neighbours of removed rolls can become accessible for the next one. when the
+    // reversed rule can't find the rolls crowded by each cell, they are listed up front
+    let reversed_rule = rule.reversed();
+    let crowded_by = (!rule.is_reversible(input)).then(|| crowded_by(input, rule, materials));
+    let mut round = 1;
+    while !wave.is_empty() {
+        let mut next_wave = Vec::new();
+        let mut uncrowd = |n_x: usize, n_y: usize| {
+            let count = &mut neighbor_counts[row_starts[n_y] + n_x];
+            if *count != SETTLED {
+                *count -= 1;
+                if (*count as usize) < rule.threshold {
+                    *count = SETTLED;
+                    next_wave.push((n_x, n_y));
+                }
+            }
+        };
+
+        for &(x, y) in &wave {
+            removed(x, y, round);
+
+            // rolls that never crowded anything don't free up their neighbours
+            if !crowds[input[y][x].0 as usize] {
+                continue;
+            }
+            match &crowded_by {
+                Some(crowded_by) => {
+                    for &(n_x, n_y) in &crowded_by[y][x] {
+                        uncrowd(n_x, n_y);
+                    }
+                }
+                None => {
+                    for (n_x, n_y) in neighbor_positions(input, x, y, &reversed_rule) {
+                        uncrowd(n_x, n_y);
+                    }
+                }
+            }
         }
+
+        wave = next_wave;
+        round += 1;
+    }
+}
+
+fn erode(input: &[Vec<Space>], rule: &AccessRule, materials: &Materials) -> Erosion {
+    let mut removal_rounds: Vec<Vec<Option<u32>>> =
+        input.iter().map(|row| vec![None; row.len()]).collect();
+    let mut final_grid = input.to_vec();
+    erode_with(input, rule, materials, |x, y, round| {
+        removal_rounds[y][x] = Some(round);
+        final_grid[y][x] = Space::EMPTY;
+    });
+
+    Erosion {
+        removal_rounds,
+        final_grid,
     }
+}
+
+fn solve2(input: &[Vec<Space>], rule: &AccessRule, materials: &Materials) -> i64 {
+    let mut total = 0;
+    erode_with(input, rule, materials, |_, _, _| total += 1);
 
     total
 }
 
+#[derive(Debug, Clone, Copy)]
+enum RenderStyle {
+    /// removed rolls as their round in base 36, `+` past round 35
+    Rounds,
+    /// removed rolls coloured from red (first round) to blue (last round)
+    Gradient,
+}
+
+fn render_grid(grid: &[Vec<Space>], materials: &Materials) -> String {
+    grid.iter()
+        .map(|row| {
+            row.iter()
+                .map(|space| materials.get(*space).symbol)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_erosion(
+    input: &[Vec<Space>],
+    erosion: &Erosion,
+    style: RenderStyle,
+    materials: &Materials,
+) -> String {
+    let last_round = erosion
+        .removal_rounds
+        .iter()
+        .flatten()
+        .flatten()
+        .max()
+        .copied()
+        .unwrap_or(1);
+
+    let mut rendered = Vec::new();
+    for (row, rounds) in input.iter().zip(&erosion.removal_rounds) {
+        let mut line = String::new();
+        for (space, round) in row.iter().zip(rounds) {
+            match (space, round) {
+                (space, None) => line.push(materials.get(*space).symbol),
+                (space, Some(round)) => match style {
+                    RenderStyle::Rounds => line.push(char::from_digit(*round, 36).unwrap_or('+')),
+                    RenderStyle::Gradient => {
+                        let t = if last_round > 1 {
+                            (round - 1) as f64 / (last_round - 1) as f64
+                        } else {
+                            0.0
+                        };
+                        let red = (255.0 * (1.0 - t)) as u8;
+                        let blue = (255.0 * t) as u8;
+                        line.push_str(&format!(
+                            "\x1b[38;2;{red};0;{blue}m{}\x1b[0m",
+                            materials.get(*space).symbol
+                        ));
+                    }
+                },
+            }
+        }
+        rendered.push(line);
+    }
+
+    rendered.join("\n")
+}
+
 fn main() {
-    let parsed = parse_input(include_str!("input.txt").lines());
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut rule = AccessRule::default();
+    let mut materials = Materials::default();
+    for arg in &args {
+        if let Some(neighborhood) = arg.strip_prefix("--neighborhood=") {
+            rule.neighborhood = match neighborhood {
+                "4" => Neighborhood::Four,
+                "8" => Neighborhood::Eight,
+                "hex" => Neighborhood::Hex,
+                // e.g. `--neighborhood=-2,0;2,0;0,-2;0,2`
+                offsets => Neighborhood::Custom(
+                    offsets
+                        .split(";")
+                        .map(|offset| {
+                            let (x, y) = offset.split_once(",").unwrap();
+                            (x.parse().unwrap(), y.parse().unwrap())
+                        })
+                        .collect(),
+                ),
+            };
+        } else if let Some(threshold) = arg.strip_prefix("--threshold=") {
+            rule.threshold = threshold.parse().unwrap();
+        } else if arg == "--wrap" {
+            rule.edges = Edges::Wrap;
+        } else if let Some(material) = arg.strip_prefix("--material=") {
+            // e.g. `--material=#:n` for a pillar, flags are `r` for removable and `n`
+            // for counting as a neighbour
+            let (symbol, flags) = material.split_once(":").unwrap_or((material, ""));
+            let mut symbol = symbol.chars();
+            let (Some(symbol), None) = (symbol.next(), symbol.next()) else {
+                panic!("Invalid material {material}");
+            };
+            materials.register(Material {
+                symbol,
+                removable: flags.contains('r'),
+                counts_as_neighbor: flags.contains('n'),
+            });
+        }
+    }
 
-    println!("Part 1: {}", solve1(&parsed));
-    println!("Part 2: {}", solve2(&parsed));
+    let parsed = parse_input(include_str!("input.txt").lines(), &materials).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    println!("Part 1: {}", solve1(&parsed, &rule, &materials));
+    println!("Part 2: {}", solve2(&parsed, &rule, &materials));
+
+    if args.iter().any(|a| a == "--rounds" || a == "--gradient") {
+        let style = if args.iter().any(|a| a == "--gradient") {
+            RenderStyle::Gradient
+        } else {
+            RenderStyle::Rounds
+        };
+        let erosion = erode(&parsed, &rule, &materials);
+        println!("{}", render_erosion(&parsed, &erosion, style, &materials));
+        println!();
+        println!("{}", render_grid(&erosion.final_grid, &materials));
+    }
 }
 
 #[cfg(test)]
@@ -132,13 +580,301 @@ mod tests {
 
     #[test]
     fn test1() {
-        let parsed = parse_input(INPUT.iter().cloned());
-        assert_eq!(solve1(&parsed), 13);
+        let parsed = parse_input(INPUT.iter().cloned(), &Materials::default()).unwrap();
+        assert_eq!(
+            solve1(&parsed, &AccessRule::default(), &Materials::default()),
+            13
+        );
     }
 
     #[test]
     fn test2() {
-        let parsed = parse_input(INPUT.iter().cloned());
-        assert_eq!(solve2(&parsed), 43);
+        let parsed = parse_input(INPUT.iter().cloned(), &Materials::default()).unwrap();
+        assert_eq!(
+            solve2(&parsed, &AccessRule::default(), &Materials::default()),
+            43
+        );
+    }
+
+    #[test]
+    fn test2_large() {
+        // only the corners of a solid block are ever accessible
+        let row = "@".repeat(1000);
+        let parsed = parse_input(
+            std::iter::repeat_n(row.as_str(), 1000),
+            &Materials::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            solve2(&parsed, &AccessRule::default(), &Materials::default()),
+            4
+        );
+
+        // rows of paper with gaps between them are all removed in the first round
+        let rows: Vec<String> = (0..1000)
+            .map(|y| {
+                if y % 2 == 0 {
+                    "@".repeat(1000)
+                } else {
+                    ".".repeat(1000)
+                }
+            })
+            .collect();
+        let parsed = parse_input(rows.iter().map(|r| r.as_str()), &Materials::default()).unwrap();
+        assert_eq!(
+            solve2(&parsed, &AccessRule::default(), &Materials::default()),
+            500 * 1000
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test2_huge() {
+        // a 5000x5000 grid that is three quarters paper, solve2 takes around half a
+        // second with `cargo test --release -- --ignored`
+        let mut seed: u64 = 1;
+        let rows: Vec<String> = (0..5000)
+            .map(|_| {
+                (0..5000)
+                    .map(|_| {
+                        seed = seed
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        if (seed >> 33).is_multiple_of(4) {
+                            '.'
+                        } else {
+                            '@'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let parsed = parse_input(rows.iter().map(|r| r.as_str()), &Materials::default()).unwrap();
+        assert_eq!(
+            solve2(&parsed, &AccessRule::default(), &Materials::default()),
+            1985711
+        );
+    }
+
+    #[test]
+    fn test_erosion() {
+        let parsed = parse_input(INPUT.iter().cloned(), &Materials::default()).unwrap();
+        let erosion = erode(&parsed, &AccessRule::default(), &Materials::default());
+        assert_eq!(erosion.removal_rounds[0][2], Some(1));
+        assert_eq!(erosion.removal_rounds[0][0], None);
+        assert_eq!(
+            render_erosion(
+                &parsed,
+                &erosion,
+                RenderStyle::Rounds,
+                &Materials::default()
+            ),
+            [
+                "..11.1121.",
+                "134.2.2.32",
+                "24578.1.33",
+                "2.69@@..2.",
+                "13.@@@@.21",
+                ".24@@@@@.2",
+                ".2.@.@.@@3",
+                "1.4@@.@@@4",
+                ".23@@@@@5.",
+                "1.1.@@@.1.",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render_grid(&erosion.final_grid, &Materials::default()),
+            [
+                "..........",
+                "..........",
+                "..........",
+                "....@@....",
+                "...@@@@...",
+                "...@@@@@..",
+                "...@.@.@@.",
+                "...@@.@@@.",
+                "...@@@@@..",
+                "....@@@...",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        let parsed = parse_input(INPUT.iter().cloned(), &Materials::default()).unwrap();
+        let custom = AccessRule {
+            neighborhood: Neighborhood::Custom(OFFSETS_8.to_vec()),
+            ..Default::default()
+        };
+        assert_eq!(solve1(&parsed, &custom, &Materials::default()), 13);
+        assert_eq!(solve2(&parsed, &custom, &Materials::default()), 43);
+
+        // the middle of the top row touches 3 rolls, the others only 1
+        let parsed = parse_input(["@@@", ".@.", "..."].into_iter(), &Materials::default()).unwrap();
+        let four = AccessRule {
+            neighborhood: Neighborhood::Four,
+            threshold: 2,
+            edges: Edges::Bounded,
+        };
+        assert_eq!(solve1(&parsed, &four, &Materials::default()), 3);
+        let erosion = erode(&parsed, &four, &Materials::default());
+        assert_eq!(erosion.removal_rounds[0], vec![Some(1), Some(2), Some(1)]);
+    }
+
+    #[test]
+    fn test_wrap() {
+        // a ring of paper has no edges once wrapped, so no roll is ever accessible
+        let parsed = parse_input(["@@@", "@@@", "@@@"].into_iter(), &Materials::default()).unwrap();
+        let wrap = AccessRule {
+            edges: Edges::Wrap,
+            ..Default::default()
+        };
+        assert_eq!(
+            solve1(&parsed, &AccessRule::default(), &Materials::default()),
+            4
+        );
+        assert_eq!(solve1(&parsed, &wrap, &Materials::default()), 0);
+
+        let parsed = parse_input(
+            ["@@..", "....", "....", "@@.."].into_iter(),
+            &Materials::default(),
+        )
+        .unwrap();
+        let four_wrap = AccessRule {
+            neighborhood: Neighborhood::Four,
+            threshold: 2,
+            edges: Edges::Wrap,
+        };
+        assert_eq!(solve1(&parsed, &four_wrap, &Materials::default()), 0);
+        assert_eq!(
+            solve1(
+                &parsed,
+                &AccessRule {
+                    edges: Edges::Bounded,
+                    ..four_wrap
+                },
+                &Materials::default()
+            ),
+            4
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        // the centre cell of an odd row touches both cells to its upper right
+        let parsed = parse_input([".@@", ".@.", "..."].into_iter(), &Materials::default()).unwrap();
+        let hex = AccessRule {
+            neighborhood: Neighborhood::Hex,
+            threshold: 2,
+            edges: Edges::Bounded,
+        };
+        let neighbors: Vec<_> = neighbor_positions(&parsed, 1, 1, &hex).collect();
+        assert_eq!(
+            neighbors,
+            vec![(1, 0), (2, 0), (0, 1), (2, 1), (1, 2), (2, 2)]
+        );
+        assert_eq!(solve1(&parsed, &hex, &Materials::default()), 0);
+    }
+
+    #[test]
+    fn test_asymmetric() {
+        // each roll only looks at the cell to its right, so the row erodes from the end
+        let parsed = parse_input(["@@@"].into_iter(), &Materials::default()).unwrap();
+        let rule = AccessRule {
+            neighborhood: Neighborhood::Custom(vec![(1, 0)]),
+            threshold: 1,
+            edges: Edges::Bounded,
+        };
+        // the offsets only go one way, but reversing them still finds the crowded rolls
+        assert!(rule.is_reversible(&parsed));
+        let erosion = erode(&parsed, &rule, &Materials::default());
+        assert_eq!(erosion.removal_rounds[0], vec![Some(3), Some(2), Some(1)]);
+    }
+
+    #[test]
+    fn test_materials() {
+        let mut materials = Materials::default();
+        let pillar = materials.register(Material {
+            symbol: '#',
+            removable: false,
+            counts_as_neighbor: false,
+        });
+        materials.register(Material {
+            symbol: 'F',
+            removable: false,
+            counts_as_neighbor: true,
+        });
+        materials.register(Material {
+            symbol: '%',
+            removable: true,
+            counts_as_neighbor: false,
+        });
+        let rule = AccessRule::default();
+
+        // the pillar doesn't crowd anything, so only the edge centres are stuck at first
+        let parsed = parse_input(["@@@", "@#@", "@@@"].into_iter(), &materials).unwrap();
+        assert_eq!(parsed[1][1], pillar);
+        assert_eq!(solve1(&parsed, &rule, &materials), 4);
+        assert_eq!(solve2(&parsed, &rule, &materials), 8);
+
+        // the fixed roll crowds its neighbours but is never removed itself
+        let parsed = parse_input(["@@@", "@F@", "@@@"].into_iter(), &materials).unwrap();
+        let erosion = erode(&parsed, &rule, &materials);
+        assert_eq!(erosion.removal_rounds[1], vec![Some(2), None, Some(2)]);
+        assert_eq!(
+            render_grid(&erosion.final_grid, &materials),
+            "...\n.F.\n..."
+        );
+
+        // rolls that don't count as neighbours leave each other accessible
+        let parsed = parse_input(["%%%", "%%%", "%%%"].into_iter(), &materials).unwrap();
+        assert_eq!(solve1(&parsed, &rule, &materials), 9);
+        let erosion = erode(&parsed, &rule, &materials);
+        assert_eq!(
+            render_erosion(&parsed, &erosion, RenderStyle::Gradient, &materials)
+                .lines()
+                .next(),
+            Some("\x1b[38;2;255;0;0m%\x1b[0m".repeat(3).as_str())
+        );
+
+        // characters without a registered material are reported where they are
+        assert_eq!(
+            parse_input(["@@@", "@X@"].into_iter(), &materials),
+            Err("unknown material 'X' at line 2, column 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_asymmetric_wrap() {
+        // wrapping an odd number of hex rows puts an even row next to an even row, so
+        // rolls no longer see each other equally often
+        let hex_wrap = AccessRule {
+            neighborhood: Neighborhood::Hex,
+            threshold: 2,
+            edges: Edges::Wrap,
+        };
+        let parsed = parse_input(["@..@"].into_iter(), &Materials::default()).unwrap();
+        assert!(!hex_wrap.is_reversible(&parsed));
+        assert_eq!(solve2(&parsed, &hex_wrap, &Materials::default()), 2);
+
+        let parsed = parse_input([".@@@@"].into_iter(), &Materials::default()).unwrap();
+        let rule = AccessRule {
+            threshold: 3,
+            ..hex_wrap
+        };
+        assert_eq!(solve2(&parsed, &rule, &Materials::default()), 4);
+
+        // wrapping ragged rows lands on different columns going up and coming back down
+        let parsed =
+            parse_input(["@", ".@", "@.", ".@"].into_iter(), &Materials::default()).unwrap();
+        let four_wrap = AccessRule {
+            neighborhood: Neighborhood::Four,
+            threshold: 1,
+            edges: Edges::Wrap,
+        };
+        assert!(!four_wrap.is_reversible(&parsed));
+        assert_eq!(solve2(&parsed, &four_wrap, &Materials::default()), 4);
     }
 }
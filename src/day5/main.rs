@@ -1,5 +1,218 @@
+use std::collections::BTreeSet;
 use std::ops::RangeInclusive;
 
+/// integers with a next and previous value, so touching ranges can be merged
+trait Discrete: Copy + Ord {
+    const MIN: Self;
+    const MAX: Self;
+
+    fn succ(self) -> Option<Self>;
+    fn pred(self) -> Option<Self>;
+}
+
+impl Discrete for i64 {
+    const MIN: Self = i64::MIN;
+    const MAX: Self = i64::MAX;
+
+    fn succ(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+
+    fn pred(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+}
+
+/// sorted, disjoint and non-touching inclusive ranges
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IntervalSet<T> {
+    ranges: Vec<RangeInclusive<T>>,
+}
+
+impl<T: Discrete> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self { ranges: Vec::new() }
+    }
+}
+
+impl<T: Discrete> FromIterator<RangeInclusive<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        let mut ranges: Vec<RangeInclusive<T>> = iter.into_iter().collect();
+        ranges.sort_unstable_by_key(|r| *r.start());
+        Self::from_sorted(ranges)
+    }
+}
+
+impl<T: Discrete> IntervalSet<T> {
+    /// merges ranges sorted by start in a single pass
+    fn from_sorted(sorted: impl IntoIterator<Item = RangeInclusive<T>>) -> Self {
+        let mut ranges: Vec<RangeInclusive<T>> = Vec::new();
+        for range in sorted {
+            if range.is_empty() {
+                continue;
+            }
+            let (start, end) = range.into_inner();
+
+            if let Some(last) = ranges.last_mut()
+                && last.end().succ().is_none_or(|after| start <= after)
+            {
+                if end > *last.end() {
+                    *last = *last.start()..=end;
+                }
+            } else {
+                ranges.push(start..=end);
+            }
+        }
+
+        Self { ranges }
+    }
+
+    /// adds a single range in place, a set is better built with `collect`
+    fn insert(&mut self, range: RangeInclusive<T>) {
+        if range.is_empty() {
+            return;
+        }
+        let (mut start, mut end) = range.into_inner();
+
+        // every range from `first` up to `last` overlaps or touches the new one
+        let first = self
+            .ranges
+            .partition_point(|r| r.end().succ().is_some_and(|after| after < start));
+        let last = self
+            .ranges
+            .partition_point(|r| end.succ().is_none_or(|after| *r.start() <= after));
+
+        if first < last {
+            start = start.min(*self.ranges[first].start());
+            end = end.max(*self.ranges[last - 1].end());
+        }
+        self.ranges.splice(first..last, [start..=end]);
+    }
+
+    fn remove(&mut self, range: RangeInclusive<T>) {
+        if range.is_empty() {
+            return;
+        }
+        let (start, end) = range.into_inner();
+
+        // every range from `first` up to `last` overlaps the removed one
+        let first = self.ranges.partition_point(|r| *r.end() < start);
+        let last = self.ranges.partition_point(|r| *r.start() <= end);
+        if first >= last {
+            return;
+        }
+
+        let mut remainders = Vec::new();
+        if let Some(before) = start.pred()
+            && *self.ranges[first].start() <= before
+        {
+            remainders.push(*self.ranges[first].start()..=before);
+        }
+        if let Some(after) = end.succ()
+            && after <= *self.ranges[last - 1].end()
+        {
+            remainders.push(after..=*self.ranges[last - 1].end());
+        }
+        self.ranges.splice(first..last, remainders);
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        let idx = self.ranges.partition_point(|r| r.start() <= value);
+        idx > 0 && self.ranges[idx - 1].end() >= value
+    }
+}
+
+// general purpose set operations, the stream applies batches of ranges with them
+impl<T: Discrete> IntervalSet<T> {
+    fn iter(&self) -> impl Iterator<Item = &RangeInclusive<T>> {
+        self.ranges.iter()
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        // both sides are already sorted, so merging them keeps the result sorted
+        let (mut a, mut b) = (self.iter().peekable(), other.iter().peekable());
+        let merged = std::iter::from_fn(|| match (a.peek(), b.peek()) {
+            (Some(range_a), Some(range_b)) if range_b.start() < range_a.start() => b.next(),
+            _ => a.next().or_else(|| b.next()),
+        });
+        Self::from_sorted(merged.cloned())
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let (mut a, mut b) = (0, 0);
+        while a < self.ranges.len() && b < other.ranges.len() {
+            let (range_a, range_b) = (&self.ranges[a], &other.ranges[b]);
+            let start = *range_a.start().max(range_b.start());
+            let end = *range_a.end().min(range_b.end());
+            if start <= end {
+                ranges.push(start..=end);
+            }
+
+            if range_a.end() < range_b.end() {
+                a += 1;
+            } else {
+                b += 1;
+            }
+        }
+
+        // pieces of two normalised sets can't touch each other
+        Self { ranges }
+    }
+
+    /// every value in `bounds` that isn't in the set
+    fn complement(&self, bounds: RangeInclusive<T>) -> Self {
+        let mut ranges = Vec::new();
+        if bounds.is_empty() {
+            return Self { ranges };
+        }
+        let (bounds_start, bounds_end) = bounds.into_inner();
+
+        // first value that hasn't been covered by a range or a gap yet
+        let mut next = Some(bounds_start);
+        for range in &self.ranges {
+            let Some(start) = next else {
+                break;
+            };
+            if *range.end() < start {
+                continue;
+            }
+            if *range.start() > bounds_end {
+                break;
+            }
+
+            if let Some(before) = range.start().pred()
+                && start <= before
+            {
+                ranges.push(start..=before);
+            }
+            next = range.end().succ();
+        }
+        if let Some(start) = next
+            && start <= bounds_end
+        {
+            ranges.push(start..=bounds_end);
+        }
+
+        Self { ranges }
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        self.intersection(&other.complement(T::MIN..=T::MAX))
+    }
+}
+
+impl IntervalSet<i64> {
+    /// number of values covered by the set, `i64::MIN..=i64::MAX` alone holds 2^64
+    /// values so this needs to be wider than the ranges themselves
+    fn len(&self) -> u128 {
+        self.ranges
+            .iter()
+            .map(|r| (*r.end() as i128 - *r.start() as i128 + 1) as u128)
+            .sum()
+    }
+}
+
 fn parse_input<'a>(
     mut input: impl Iterator<Item = &'a str>,
 ) -> (Vec<RangeInclusive<i64>>, Vec<i64>) {
@@ -20,55 +233,195 @@ fn parse_input<'a>(
 }
 
 fn solve1(input: &(Vec<RangeInclusive<i64>>, Vec<i64>)) -> usize {
+    let fresh: IntervalSet<i64> = input.0.iter().cloned().collect();
     input
         .1
         .iter()
-        .filter(|ingredient| input.0.iter().any(|range| range.contains(*ingredient)))
+        .filter(|ingredient| fresh.contains(ingredient))
         .count()
 }
 
-fn solve2(input: &(Vec<RangeInclusive<i64>>, Vec<i64>)) -> usize {
-    let mut ranges = input.0.clone();
-    ranges.sort_by(|a, b| {
-        if a.start() != b.start() {
-            (*a.start()).cmp(b.start())
-        } else {
-            (*a.end()).cmp(b.end())
-        }
-    });
+fn solve2(input: &(Vec<RangeInclusive<i64>>, Vec<i64>)) -> u128 {
+    let fresh: IntervalSet<i64> = input.0.iter().cloned().collect();
+    fresh.len()
+}
 
-    let mut new_ranges = vec![ranges[0].clone()];
-    for i in ranges.into_iter().skip(1) {
-        let prev = new_ranges.last().unwrap();
+#[derive(Debug, PartialEq, Eq)]
+struct QueryReport {
+    /// indices of the input ranges containing each queried id, in query order
+    containing: Vec<Vec<usize>>,
+    /// number of queried ids in each input range
+    range_hits: Vec<usize>,
+    /// queried ids that aren't in any range
+    unmatched: Vec<i64>,
+}
+
+fn query(ranges: &[RangeInclusive<i64>], ids: &[i64]) -> QueryReport {
+    let mut sorted_ids = ids.to_vec();
+    sorted_ids.sort_unstable();
+    let range_hits = ranges
+        .iter()
+        .map(|r| {
+            // ranges with start past their end are empty and contain nothing
+            if r.is_empty() {
+                return 0;
+            }
+            sorted_ids.partition_point(|id| id <= r.end())
+                - sorted_ids.partition_point(|id| id < r.start())
+        })
+        .collect();
 
-        let new_start = if *i.start() < *prev.start() {
-            *prev.end() + 1
-        } else {
-            *i.start()
-        };
+    // sweep the ids in order, keeping the ranges that have started but not ended yet
+    let mut by_start: Vec<usize> = (0..ranges.len()).collect();
+    by_start.sort_unstable_by_key(|&i| *ranges[i].start());
+    let mut query_order: Vec<usize> = (0..ids.len()).collect();
+    query_order.sort_unstable_by_key(|&i| ids[i]);
 
-        if prev.contains(&new_start) && prev.contains(i.end()) {
-            // redundant range, continue
-            continue;
+    let mut containing = vec![Vec::new(); ids.len()];
+    let mut active = BTreeSet::new();
+    let mut next_range = 0;
+    for query_idx in query_order {
+        let id = ids[query_idx];
+        while next_range < by_start.len() && *ranges[by_start[next_range]].start() <= id {
+            let range_idx = by_start[next_range];
+            active.insert((*ranges[range_idx].end(), range_idx));
+            next_range += 1;
         }
-
-        if !prev.contains(&new_start) && !prev.contains(i.end()) {
-            // disjoint, this range can just be added
-            new_ranges.push(new_start..=*i.end());
-            continue;
+        while active.first().is_some_and(|&(end, _)| end < id) {
+            active.pop_first();
         }
 
-        new_ranges.push(*prev.end() + 1..=*i.end());
+        let mut range_idxs: Vec<usize> = active.iter().map(|&(_, i)| i).collect();
+        range_idxs.sort_unstable();
+        containing[query_idx] = range_idxs;
+    }
+
+    let unmatched = ids
+        .iter()
+        .zip(&containing)
+        .filter(|(_, range_idxs)| range_idxs.is_empty())
+        .map(|(id, _)| *id)
+        .collect();
+
+    QueryReport {
+        containing,
+        range_hits,
+        unmatched,
     }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    /// one or more comma separated ranges
+    Add(Vec<RangeInclusive<i64>>),
+    Remove(Vec<RangeInclusive<i64>>),
+    Query(i64),
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let parse_id = |id: &str| {
+        id.parse()
+            .map_err(|e| format!("invalid id `{id}` in `{line}`: {e}"))
+    };
+    // the separator is the first `-` after the start, which may be negative itself
+    let parse_range = |range: &str| {
+        let (sep, _) = range
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c == '-')
+            .ok_or_else(|| format!("invalid range `{range}`: expected `start-end`"))?;
+        Ok::<_, String>(parse_id(&range[..sep])?..=parse_id(&range[sep + 1..])?)
+    };
+
+    let parse_ranges = |ranges: &str| {
+        ranges
+            .split(",")
+            .map(parse_range)
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    if let Some(ranges) = line.strip_prefix("+") {
+        Ok(Command::Add(parse_ranges(ranges)?))
+    } else if let Some(ranges) = line.strip_prefix("-") {
+        Ok(Command::Remove(parse_ranges(ranges)?))
+    } else if let Some(id) = line.strip_prefix("?") {
+        Ok(Command::Query(parse_id(id)?))
+    } else {
+        Err(format!(
+            "invalid command `{line}`: expected `+`, `-` or `?`"
+        ))
+    }
+}
 
-    new_ranges.into_iter().map(|r| r.count()).sum()
+/// applies each command as it arrives, yielding whether each queried id is fresh.
+/// malformed lines are yielded as errors and otherwise skipped
+fn run_stream<S: AsRef<str>>(
+    input: impl Iterator<Item = S>,
+) -> impl Iterator<Item = Result<(i64, bool), String>> {
+    let mut fresh = IntervalSet::default();
+    input.filter_map(move |line| {
+        let line = line.as_ref().trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        match parse_command(line) {
+            // single ranges are updated in place, batches are merged in one pass
+            Ok(Command::Add(mut ranges)) => {
+                if ranges.len() == 1 {
+                    fresh.insert(ranges.remove(0));
+                } else {
+                    fresh = fresh.union(&ranges.into_iter().collect());
+                }
+                None
+            }
+            Ok(Command::Remove(mut ranges)) => {
+                if ranges.len() == 1 {
+                    fresh.remove(ranges.remove(0));
+                } else {
+                    fresh = fresh.difference(&ranges.into_iter().collect());
+                }
+                None
+            }
+            Ok(Command::Query(id)) => Some(Ok((id, fresh.contains(&id)))),
+            Err(e) => Some(Err(e)),
+        }
+    })
 }
 
 fn main() {
+    if std::env::args().any(|a| a == "--stream") {
+        let lines = std::io::stdin().lines().map_while(Result::ok);
+        for answer in run_stream(lines) {
+            match answer {
+                Ok((id, is_fresh)) => {
+                    println!("{id}: {}", if is_fresh { "fresh" } else { "spoiled" })
+                }
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        return;
+    }
+
     let parsed = parse_input(include_str!("input.txt").lines());
 
     println!("Part 1: {}", solve1(&parsed));
     println!("Part 2: {}", solve2(&parsed));
+
+    if std::env::args().any(|a| a == "--report") {
+        let report = query(&parsed.0, &parsed.1);
+        for (id, range_idxs) in parsed.1.iter().zip(&report.containing) {
+            let ranges: Vec<String> = range_idxs
+                .iter()
+                .map(|&i| format!("{}-{}", parsed.0[i].start(), parsed.0[i].end()))
+                .collect();
+            println!("{id}: [{}]", ranges.join(", "));
+        }
+        for (range, hits) in parsed.0.iter().zip(&report.range_hits) {
+            println!("{}-{}: {hits} hits", range.start(), range.end());
+        }
+        println!("Unmatched: {:?}", report.unmatched);
+    }
 }
 
 #[cfg(test)]
@@ -90,4 +443,158 @@ mod tests {
         let parsed = parse_input(INPUT.iter().cloned());
         assert_eq!(solve2(&parsed), 14);
     }
+
+    #[test]
+    fn test_insert() {
+        let mut set: IntervalSet<i64> = [10..=14, 3..=5, 16..=20, 12..=18].into_iter().collect();
+        assert_eq!(set.ranges, vec![3..=5, 10..=20]);
+
+        // touching ranges merge, empty ones are ignored
+        set.insert(6..=9);
+        let (start, end) = (30, 25);
+        set.insert(start..=end);
+        assert_eq!(set.ranges, vec![3..=20]);
+        assert!(set.contains(&3) && set.contains(&20));
+        assert!(!set.contains(&2) && !set.contains(&21));
+    }
+
+    #[test]
+    fn test_collect_large() {
+        // disjoint ranges in descending order used to be spliced in one at a time
+        let ranges: Vec<RangeInclusive<i64>> =
+            (0..200_000).rev().map(|i| i * 10..=i * 10 + 4).collect();
+        let fresh: IntervalSet<i64> = ranges.iter().cloned().collect();
+        assert_eq!(fresh.ranges.len(), 200_000);
+        assert_eq!(fresh.len(), 200_000 * 5);
+
+        // and touching ranges still merge into one
+        let fresh: IntervalSet<i64> = (0..200_000).rev().map(|i| i * 5..=i * 5 + 4).collect();
+        assert_eq!(fresh.ranges, vec![0..=999_999]);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: IntervalSet<i64> = [1..=5, 10..=15].into_iter().collect();
+        let b: IntervalSet<i64> = [4..=11, 20..=20].into_iter().collect();
+
+        assert_eq!(a.union(&b).ranges, vec![1..=15, 20..=20]);
+        assert_eq!(a.intersection(&b).ranges, vec![4..=5, 10..=11]);
+        assert_eq!(a.difference(&b).ranges, vec![1..=3, 12..=15]);
+        assert_eq!(b.difference(&a).ranges, vec![6..=9, 20..=20]);
+        assert_eq!(a.complement(0..=12).ranges, vec![0..=0, 6..=9]);
+        assert_eq!(
+            a.complement(i64::MIN..=i64::MAX).ranges,
+            vec![i64::MIN..=0, 6..=9, 16..=i64::MAX]
+        );
+        assert_eq!(a.len(), 11);
+    }
+
+    #[test]
+    fn test_extreme_lengths() {
+        let full: IntervalSet<i64> = [i64::MIN..=i64::MAX].into_iter().collect();
+        assert_eq!(full.len(), 1 << 64);
+
+        let halves: IntervalSet<i64> = [0..=i64::MAX, i64::MIN..=-1].into_iter().collect();
+        assert_eq!(halves, full);
+
+        let ends: IntervalSet<i64> = [i64::MIN..=i64::MIN, i64::MAX..=i64::MAX, i64::MIN..=0]
+            .into_iter()
+            .collect();
+        assert_eq!(ends.len(), (1 << 63) + 2);
+        assert_eq!(full.difference(&ends).len(), (1 << 63) - 2);
+
+        let parsed = parse_input(["0-9223372036854775807", "5-10", ""].into_iter());
+        assert_eq!(solve2(&parsed), 1 << 63);
+    }
+
+    #[test]
+    fn test_query() {
+        let parsed = parse_input(INPUT.iter().cloned());
+        let report = query(&parsed.0, &parsed.1);
+        assert_eq!(
+            report.containing,
+            vec![vec![], vec![0], vec![], vec![1], vec![2, 3], vec![]]
+        );
+        assert_eq!(report.range_hits, vec![1, 1, 1, 1]);
+        assert_eq!(report.unmatched, vec![1, 8, 32]);
+
+        // repeated ids are reported every time
+        let report = query(&[1..=10, 5..=5], &[5, 5, 11]);
+        assert_eq!(report.containing, vec![vec![0, 1], vec![0, 1], vec![]]);
+        assert_eq!(report.range_hits, vec![2, 2]);
+        assert_eq!(report.unmatched, vec![11]);
+
+        // backwards ranges are accepted but empty
+        let (start, end) = (5, 3);
+        let report = query(&[start..=end], &[4]);
+        assert_eq!(report.containing, vec![vec![]]);
+        assert_eq!(report.range_hits, vec![0]);
+        assert_eq!(report.unmatched, vec![4]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut set: IntervalSet<i64> = [1..=10, 20..=30].into_iter().collect();
+        set.remove(5..=5);
+        assert_eq!(set.ranges, vec![1..=4, 6..=10, 20..=30]);
+        set.remove(8..=25);
+        assert_eq!(set.ranges, vec![1..=4, 6..=7, 26..=30]);
+        set.remove(0..=100);
+        assert_eq!(set.ranges, vec![]);
+
+        let mut full: IntervalSet<i64> = [i64::MIN..=i64::MAX].into_iter().collect();
+        full.remove(i64::MIN..=0);
+        full.remove(i64::MAX..=i64::MAX);
+        assert_eq!(full.ranges, vec![1..=i64::MAX - 1]);
+    }
+
+    #[test]
+    fn test_stream() {
+        let input = ["+3-5", "?4", "-4-4", "?4", "?5", "", "+1-10", "?4", "?11"];
+        let answers: Vec<(i64, bool)> = run_stream(input.into_iter()).map(Result::unwrap).collect();
+        assert_eq!(
+            answers,
+            vec![(4, true), (4, false), (5, true), (4, true), (11, false)]
+        );
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        // bad lines are reported without ending the stream
+        let input = ["+3 - 5", "?x", "+1-10", "*4", "?4"];
+        let answers: Vec<_> = run_stream(input.into_iter()).collect();
+        assert_eq!(answers.len(), 4);
+        assert!(answers[0].as_ref().unwrap_err().contains("`3 `"));
+        assert!(answers[1].is_err());
+        assert!(answers[2].is_err());
+        assert_eq!(answers[3], Ok((4, true)));
+
+        // negative bounds split at the separator rather than the sign
+        assert_eq!(parse_command("+-5--3"), Ok(Command::Add(vec![-5..=-3])));
+        assert_eq!(parse_command("--5-3"), Ok(Command::Remove(vec![-5..=3])));
+        assert_eq!(parse_command("?-4"), Ok(Command::Query(-4)));
+        assert!(parse_command("+5").is_err());
+    }
+
+    #[test]
+    fn test_stream_batches() {
+        let input = [
+            "+1-3,10-20,5-6",
+            "?6",
+            "--5--1,4-4,15-30",
+            "?4",
+            "?12",
+            "?15",
+        ];
+        let answers: Vec<(i64, bool)> = run_stream(input.into_iter()).map(Result::unwrap).collect();
+        assert_eq!(
+            answers,
+            vec![(6, true), (4, false), (12, true), (15, false)]
+        );
+        assert_eq!(
+            parse_command("+1-3,5-6"),
+            Ok(Command::Add(vec![1..=3, 5..=6]))
+        );
+        assert!(parse_command("+1-3,").is_err());
+    }
 }
@@ -1,109 +1,415 @@
-#[derive(Debug)]
+use std::ops::Range;
+
+use num_bigint::BigInt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Operation {
     Add,
     Mult,
+    Sub,
+    Div,
+    Mod,
+    Min,
+    Max,
 }
 
-mod part1 {
-    use super::Operation;
-
-    pub fn parse_input<'a>(
-        input: impl Iterator<Item = &'a str>,
-    ) -> (Vec<Vec<i64>>, Vec<Operation>) {
-        let mut number_lines = Vec::new();
-        let mut operations = Vec::new();
-
-        for line in input {
-            if line.trim().chars().next().unwrap().is_ascii_digit() {
-                number_lines.push(
-                    line.split_ascii_whitespace()
-                        .map(|s| s.parse().unwrap())
-                        .collect(),
-                );
-            } else {
-                operations = line
-                    .split_ascii_whitespace()
-                    .map(|o| match o {
-                        "+" => Operation::Add,
-                        "*" => Operation::Mult,
-                        _ => panic!("Invalid operation: {o}"),
-                    })
-                    .collect();
-            }
+impl Operation {
+    fn from_symbol(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Operation::Add),
+            '*' => Some(Operation::Mult),
+            '-' => Some(Operation::Sub),
+            '/' => Some(Operation::Div),
+            '%' => Some(Operation::Mod),
+            '<' => Some(Operation::Min),
+            '>' => Some(Operation::Max),
+            _ => None,
         }
-
-        (number_lines, operations)
     }
 
-    pub fn solve1(input: &(Vec<Vec<i64>>, Vec<Operation>)) -> i64 {
-        let mut total = 0;
+    fn symbol(&self) -> &'static str {
+        match self {
+            Operation::Add => "+",
+            Operation::Mult => "*",
+            Operation::Sub => "-",
+            Operation::Div => "/",
+            Operation::Mod => "%",
+            Operation::Min => "min",
+            Operation::Max => "max",
+        }
+    }
 
-        for i in 0..input.1.len() {
-            let vals = input.0.iter().map(|inp| inp[i]);
-            total += match input.1[i] {
-                Operation::Add => vals.sum::<i64>(),
-                Operation::Mult => vals.product::<i64>(),
-            };
+    /// folds the operands left to right, so `a - b - c` is `(a - b) - c`
+    fn apply(&self, operands: &[BigInt]) -> Result<BigInt, EvalError> {
+        match self {
+            Operation::Add => Ok(operands.iter().sum()),
+            Operation::Mult => Ok(operands.iter().product()),
+            _ => {
+                let (first, rest) = operands.split_first().ok_or(EvalError::NoOperands(*self))?;
+                rest.iter().try_fold(first.clone(), |acc, num| match self {
+                    Operation::Sub => Ok(acc - num),
+                    Operation::Div | Operation::Mod if *num == BigInt::ZERO => {
+                        Err(EvalError::DivisionByZero)
+                    }
+                    Operation::Div => Ok(acc / num),
+                    Operation::Mod => Ok(acc % num),
+                    Operation::Min => Ok(acc.min(num.clone())),
+                    Operation::Max => Ok(acc.max(num.clone())),
+                    Operation::Add | Operation::Mult => unreachable!(),
+                })
+            }
         }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EvalError {
+    NoOperands(Operation),
+    DivisionByZero,
+}
+
+/// an evaluation error in the problem starting at `column`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProblemError {
+    column: usize,
+    error: EvalError,
+}
 
-        total
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::NoOperands(operation) => {
+                write!(f, "no operands for {}", operation.symbol())
+            }
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+        }
     }
 }
 
-fn solve2<'a>(input: impl Iterator<Item = &'a str>) -> i64 {
-    let mut grid: Vec<Vec<char>> = Vec::new();
-    for line in input {
-        grid.push(line.chars().collect());
+impl std::fmt::Display for ProblemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "problem at column {}: {}", self.column, self.error)
     }
+}
 
-    let operations_line = {
-        let mut operations_line = grid.pop().unwrap();
-        operations_line.push('!');
-        operations_line
-    };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseError {
+    /// the worksheet has no lines to take the operators from
+    MissingOperations,
+    /// an unknown operator in the operations line at `column`
+    InvalidOperation { column: usize, symbol: char },
+}
 
-    let mut total = 0;
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingOperations => write!(f, "missing operations line"),
+            ParseError::InvalidOperation { column, symbol } => {
+                write!(f, "invalid operation {symbol:?} at column {column}")
+            }
+        }
+    }
+}
 
-    let mut cur_operation = Operation::Add;
-    let mut cur_nums = Vec::new();
-    for (i, chr) in operations_line.into_iter().enumerate() {
-        if chr != ' ' {
-            // eval accumulated nums
-            total += match cur_operation {
-                Operation::Add => cur_nums.iter().sum::<i64>(),
-                Operation::Mult => cur_nums.iter().product::<i64>(),
-            };
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Num(BigInt),
+    Apply(Operation, Vec<Expr>),
+}
 
-            cur_operation = match chr {
-                '+' => Operation::Add,
-                '*' => Operation::Mult,
-                '!' => break,
-                _ => panic!("invalid operation: {chr}"),
-            };
-            cur_nums.clear();
+impl Expr {
+    /// exact value of the expression, worksheets can easily outgrow `i64`
+    fn evaluate(&self) -> Result<BigInt, EvalError> {
+        match self {
+            Expr::Num(num) => Ok(num.clone()),
+            Expr::Apply(operation, operands) => {
+                let operands = operands
+                    .iter()
+                    .map(|o| o.evaluate())
+                    .collect::<Result<Vec<_>, _>>()?;
+                operation.apply(&operands)
+            }
         }
+    }
+}
 
-        let mut num = 0;
-        for row in &grid {
-            let cur_num_chr = row[i];
-            if cur_num_chr.is_ascii_digit() {
-                num = num * 10 + cur_num_chr.to_digit(10).unwrap() as i64;
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Num(num) => write!(f, "{num}"),
+            Expr::Apply(operation, operands) => {
+                write!(f, "(")?;
+                for (i, operand) in operands.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " {} ", operation.symbol())?;
+                    }
+                    write!(f, "{operand}")?;
+                }
+                write!(f, ")")
             }
         }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// one number per row, as humans write them
+    Rows,
+    /// one number per column read top to bottom, columns taken right to left
+    Columns,
+}
+
+/// number made of the digits among `cells`, `None` when there aren't any
+fn digits_to_num<'a>(cells: impl Iterator<Item = &'a char>) -> Option<BigInt> {
+    cells
+        .filter_map(|c| c.to_digit(10))
+        .fold(None, |num, digit| {
+            Some(num.unwrap_or(BigInt::ZERO) * 10 + digit)
+        })
+}
+
+#[derive(Debug)]
+struct Problem {
+    /// worksheet columns the problem occupies
+    columns: Range<usize>,
+    operation: Operation,
+    /// the number rows cut down to `columns`, padded with spaces
+    cells: Vec<Vec<char>>,
+}
+
+impl Problem {
+    fn operands(&self, direction: Direction) -> Vec<BigInt> {
+        match direction {
+            Direction::Rows => self
+                .cells
+                .iter()
+                .filter_map(|row| digits_to_num(row.iter()))
+                .collect(),
+            Direction::Columns => (0..self.columns.len())
+                .rev()
+                .filter_map(|col| digits_to_num(self.cells.iter().map(|row| &row[col])))
+                .collect(),
+        }
+    }
+
+    fn expression(&self, direction: Direction) -> Expr {
+        Expr::Apply(
+            self.operation,
+            self.operands(direction)
+                .into_iter()
+                .map(Expr::Num)
+                .collect(),
+        )
+    }
+
+    fn evaluate(&self, direction: Direction) -> Result<BigInt, ProblemError> {
+        self.expression(direction)
+            .evaluate()
+            .map_err(|error| ProblemError {
+                column: self.columns.start,
+                error,
+            })
+    }
+}
+
+#[derive(Debug)]
+struct Worksheet {
+    problems: Vec<Problem>,
+}
 
-        if num != 0 {
-            cur_nums.push(num);
+const TAB_WIDTH: usize = 8;
+
+/// the characters of `line` with tabs replaced by spaces up to the next tab stop
+fn expand_tabs(line: &str) -> Vec<char> {
+    let mut chars = Vec::new();
+    for c in line.chars() {
+        if c == '\t' {
+            let padding = TAB_WIDTH - chars.len() % TAB_WIDTH;
+            chars.extend(std::iter::repeat_n(' ', padding));
+        } else {
+            chars.push(c);
         }
     }
 
-    total
+    chars
+}
+
+fn parse_input<'a>(input: impl Iterator<Item = &'a str>) -> Result<Worksheet, ParseError> {
+    // lines can be ragged, anything past the end of a line is treated as blank
+    let mut grid: Vec<Vec<char>> = input
+        .filter(|line| !line.trim().is_empty())
+        .map(expand_tabs)
+        .collect();
+    let operations_line = grid.pop().ok_or(ParseError::MissingOperations)?;
+    let width = grid
+        .iter()
+        .chain([&operations_line])
+        .map(|row| row.len())
+        .max()
+        .unwrap_or(0);
+
+    // every problem starts at its operator and runs until the next one
+    let starts: Vec<(usize, Operation)> = operations_line
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(column, &symbol)| {
+            Operation::from_symbol(symbol)
+                .map(|operation| (column, operation))
+                .ok_or(ParseError::InvalidOperation { column, symbol })
+        })
+        .collect::<Result<_, _>>()?;
+
+    let problems = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &(start, operation))| {
+            let mut end = starts.get(i + 1).map_or(width, |next| next.0);
+
+            // drop the blank separator columns
+            let is_blank = |col: usize| {
+                grid.iter()
+                    .all(|row| row.get(col).is_none_or(|c| c.is_whitespace()))
+            };
+            while end > start + 1 && is_blank(end - 1) {
+                end -= 1;
+            }
+
+            let cells = grid
+                .iter()
+                .map(|row| {
+                    (start..end)
+                        .map(|col| row.get(col).copied().unwrap_or(' '))
+                        .collect()
+                })
+                .collect();
+
+            Problem {
+                columns: start..end,
+                operation,
+                cells,
+            }
+        })
+        .collect();
+
+    Ok(Worksheet { problems })
+}
+
+fn solve1(input: &Worksheet) -> Result<BigInt, ProblemError> {
+    input
+        .problems
+        .iter()
+        .map(|p| p.evaluate(Direction::Rows))
+        .sum()
+}
+
+fn solve2(input: &Worksheet) -> Result<BigInt, ProblemError> {
+    input
+        .problems
+        .iter()
+        .map(|p| p.evaluate(Direction::Columns))
+        .sum()
+}
+
+#[derive(Debug)]
+struct ProblemResult {
+    columns: Range<usize>,
+    operation: Operation,
+    operands: Vec<BigInt>,
+    value: Result<BigInt, EvalError>,
+}
+
+fn results(input: &Worksheet, direction: Direction) -> Vec<ProblemResult> {
+    input
+        .problems
+        .iter()
+        .map(|problem| {
+            let expression = problem.expression(direction);
+            ProblemResult {
+                columns: problem.columns.clone(),
+                operation: problem.operation,
+                operands: problem.operands(direction),
+                value: expression.evaluate(),
+            }
+        })
+        .collect()
+}
+
+fn print_results(results: &[ProblemResult]) {
+    for result in results {
+        let operands: Vec<String> = result.operands.iter().map(|o| o.to_string()).collect();
+        let value = match &result.value {
+            Ok(value) => value.to_string(),
+            Err(e) => e.to_string(),
+        };
+        println!(
+            "columns {}-{}: {} [{}] = {value}",
+            result.columns.start,
+            result.columns.end - 1,
+            result.operation.symbol(),
+            operands.join(", ")
+        );
+    }
+}
+
+/// one line per problem, operands are space separated so no field needs quoting
+fn to_csv(results: &[ProblemResult]) -> String {
+    let mut csv = String::from("first_column,last_column,operator,operands,value\n");
+    for result in results {
+        let operands: Vec<String> = result.operands.iter().map(|o| o.to_string()).collect();
+        let value = match &result.value {
+            Ok(value) => value.to_string(),
+            Err(e) => format!("error: {e}"),
+        };
+        csv.push_str(&format!(
+            "{},{},{},{},{value}\n",
+            result.columns.start,
+            result.columns.end - 1,
+            result.operation.symbol(),
+            operands.join(" ")
+        ));
+    }
+
+    csv
 }
 
 fn main() {
-    let parsed = part1::parse_input(include_str!("input.txt").lines());
+    let parsed = parse_input(include_str!("input.txt").lines()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    match solve1(&parsed) {
+        Ok(total) => println!("Part 1: {total}"),
+        Err(e) => println!("Part 1: {e}"),
+    }
+    match solve2(&parsed) {
+        Ok(total) => println!("Part 2: {total}"),
+        Err(e) => println!("Part 2: {e}"),
+    }
 
-    println!("Part 1: {}", part1::solve1(&parsed));
-    println!("Part 2: {}", solve2(include_str!("input.txt").lines()));
+    for arg in std::env::args().skip(1) {
+        let direction = |name: &str| match name {
+            "rows" => Direction::Rows,
+            "columns" => Direction::Columns,
+            _ => panic!("Invalid direction: {name}"),
+        };
+        if let Some(name) = arg.strip_prefix("--list=") {
+            print_results(&results(&parsed, direction(name)));
+        } else if let Some(name) = arg.strip_prefix("--csv=") {
+            print!("{}", to_csv(&results(&parsed, direction(name))));
+        }
+    }
+
+    if std::env::args().any(|a| a == "--expressions") {
+        for problem in &parsed.problems {
+            println!(
+                "{} | {}",
+                problem.expression(Direction::Rows),
+                problem.expression(Direction::Columns)
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -119,12 +425,192 @@ mod tests {
 
     #[test]
     fn test1() {
-        let parsed = part1::parse_input(INPUT.iter().cloned());
-        assert_eq!(part1::solve1(&parsed), 4277556);
+        let parsed = parse_input(INPUT.iter().cloned()).unwrap();
+        assert_eq!(solve1(&parsed), Ok(4277556.into()));
     }
 
     #[test]
     fn test2() {
-        assert_eq!(solve2(INPUT.iter().cloned()), 3263827);
+        let parsed = parse_input(INPUT.iter().cloned()).unwrap();
+        assert_eq!(solve2(&parsed), Ok(3263827.into()));
+    }
+
+    #[test]
+    fn test_worksheet() {
+        let parsed = parse_input(INPUT.iter().cloned()).unwrap();
+        let columns: Vec<Range<usize>> =
+            parsed.problems.iter().map(|p| p.columns.clone()).collect();
+        assert_eq!(columns, vec![0..3, 4..7, 8..11, 12..15]);
+
+        let first = &parsed.problems[0];
+        assert_eq!(first.operation, Operation::Mult);
+        assert_eq!(
+            first.operands(Direction::Rows),
+            vec![123.into(), 45.into(), 6.into()]
+        );
+        assert_eq!(
+            first.operands(Direction::Columns),
+            vec![356.into(), 24.into(), 1.into()]
+        );
+        assert_eq!(
+            parsed.problems[3].operands(Direction::Columns),
+            vec![4.into(), 431.into(), 623.into()]
+        );
+    }
+
+    #[test]
+    fn test_expressions() {
+        let parsed = parse_input(INPUT.iter().cloned()).unwrap();
+        let expression = parsed.problems[0].expression(Direction::Rows);
+        assert_eq!(expression.to_string(), "(123 * 45 * 6)");
+        assert_eq!(expression.evaluate(), Ok(33210.into()));
+        assert_eq!(
+            parsed.problems[1]
+                .expression(Direction::Columns)
+                .to_string(),
+            "(8 + 248 + 369)"
+        );
+    }
+
+    #[test]
+    fn test_operations() {
+        let input = [
+            "100 100 100 7 7",
+            " 30   7   7 3 9",
+            "  5   3   3 4 2",
+            "-   /   %   < >",
+        ];
+        let parsed = parse_input(input.iter().cloned()).unwrap();
+        let values: Vec<BigInt> = parsed
+            .problems
+            .iter()
+            .map(|p| p.evaluate(Direction::Rows).unwrap())
+            .collect();
+        let expected: Vec<BigInt> = [65, 4, 2, 3, 9].into_iter().map(BigInt::from).collect();
+        assert_eq!(values, expected);
+        assert_eq!(
+            parsed.problems[3].expression(Direction::Rows).to_string(),
+            "(7 min 3 min 4)"
+        );
+    }
+
+    #[test]
+    fn test_large_products() {
+        let input = [
+            "9999999999 1",
+            "9999999999 1",
+            "9999999999 0",
+            "*          /",
+        ];
+        let parsed = parse_input(input.iter().cloned()).unwrap();
+        let expected: BigInt = "999999999700000000029999999999".parse().unwrap();
+        assert_eq!(parsed.problems[0].evaluate(Direction::Rows), Ok(expected));
+        assert_eq!(
+            solve1(&parsed),
+            Err(ProblemError {
+                column: 11,
+                error: EvalError::DivisionByZero
+            })
+        );
+
+        // 25 rows of 9s make 25 digit numbers when read by column
+        let rows = vec!["99"; 25];
+        let parsed = parse_input(rows.into_iter().chain(["* "])).unwrap();
+        let column: BigInt = "9".repeat(25).parse().unwrap();
+        assert_eq!(solve2(&parsed), Ok(&column * &column));
+        assert!(solve1(&parsed).unwrap() > BigInt::from(i64::MAX));
+    }
+
+    #[test]
+    fn test_ragged_lines() {
+        // the same worksheet with trailing whitespace trimmed and a trailing blank line
+        let trimmed: Vec<&str> = INPUT
+            .iter()
+            .map(|line| line.trim_end())
+            .chain([""])
+            .collect();
+        let parsed = parse_input(trimmed.into_iter()).unwrap();
+        assert_eq!(solve1(&parsed), Ok(4277556.into()));
+        assert_eq!(solve2(&parsed), Ok(3263827.into()));
+
+        // number lines running past the operations line
+        let parsed = parse_input(["1 23", "4 56", "+ *"].into_iter()).unwrap();
+        assert_eq!(parsed.problems[1].columns, 2..4);
+        assert_eq!(solve1(&parsed), Ok((5 + 23 * 56).into()));
+        assert_eq!(solve2(&parsed), Ok((14 + 36 * 25).into()));
+    }
+
+    #[test]
+    fn test_zero_operands() {
+        // a column of zeros is a number, not a gap between numbers
+        let parsed = parse_input(["30", "00", "* "].into_iter()).unwrap();
+        assert_eq!(
+            parsed.problems[0].operands(Direction::Columns),
+            vec![0.into(), 30.into()]
+        );
+        assert_eq!(solve1(&parsed), Ok(0.into()));
+        assert_eq!(solve2(&parsed), Ok(0.into()));
+    }
+
+    #[test]
+    fn test_tabs() {
+        let parsed = parse_input(["123\t4", " 45\t56", "*\t+"].into_iter()).unwrap();
+        let columns: Vec<Range<usize>> =
+            parsed.problems.iter().map(|p| p.columns.clone()).collect();
+        assert_eq!(columns, vec![0..3, 8..10]);
+        assert_eq!(solve1(&parsed), Ok((123 * 45 + 4 + 56).into()));
+        assert_eq!(solve2(&parsed), Ok((35 * 24 + 6 + 45).into()));
+    }
+
+    #[test]
+    fn test_csv() {
+        let parsed = parse_input(INPUT.iter().cloned()).unwrap();
+        assert_eq!(
+            to_csv(&results(&parsed, Direction::Columns)),
+            [
+                "first_column,last_column,operator,operands,value",
+                "0,2,*,356 24 1,8544",
+                "4,6,+,8 248 369,625",
+                "8,10,*,175 581 32,3253600",
+                "12,14,+,4 431 623,1058",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let parsed = parse_input(["8 1", "2 0", "/ %"].into_iter()).unwrap();
+        assert_eq!(
+            to_csv(&results(&parsed, Direction::Rows)),
+            [
+                "first_column,last_column,operator,operands,value",
+                "0,0,/,8 2,4",
+                "2,2,%,1 0,error: division by zero",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_input(["1 2", "3 4", "+ ^"].into_iter()).unwrap_err(),
+            ParseError::InvalidOperation {
+                column: 2,
+                symbol: '^'
+            }
+        );
+        assert_eq!(
+            parse_input(["", "  "].into_iter()).unwrap_err(),
+            ParseError::MissingOperations
+        );
+        assert_eq!(
+            ParseError::InvalidOperation {
+                column: 2,
+                symbol: '^'
+            }
+            .to_string(),
+            "invalid operation '^' at column 2"
+        );
     }
 }
@@ -1,84 +1,380 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-fn parse_input<'a>(mut input: impl Iterator<Item = &'a str>) -> (usize, Vec<Vec<usize>>) {
-    let start_idx = input
-        .next()
-        .unwrap()
-        .chars()
-        .enumerate()
-        .find_map(|c| if c.1 == 'S' { Some(c.0) } else { None })
-        .unwrap();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Component {
+    /// `S`, emits a new beam downwards
+    Source,
+    /// `^`, splits a beam into the columns either side
+    Splitter,
+    /// `/`, deflects a beam one column to the left
+    DeflectLeft,
+    /// `\`, deflects a beam one column to the right
+    DeflectRight,
+    /// `#`, stops a beam
+    Absorber,
+}
+
+impl Component {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '.' => None,
+            'S' => Some(Component::Source),
+            '^' => Some(Component::Splitter),
+            '/' => Some(Component::DeflectLeft),
+            '\\' => Some(Component::DeflectRight),
+            '#' => Some(Component::Absorber),
+            _ => panic!("Invalid char {c}"),
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Component::Source => 'S',
+            Component::Splitter => '^',
+            Component::DeflectLeft => '/',
+            Component::DeflectRight => '\\',
+            Component::Absorber => '#',
+        }
+    }
+
+    /// column offsets a beam hitting this component continues in
+    fn deltas(self) -> &'static [isize] {
+        match self {
+            Component::Source => &[0],
+            Component::Splitter => &[-1, 1],
+            Component::DeflectLeft => &[-1],
+            Component::DeflectRight => &[1],
+            Component::Absorber => &[],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Manifold {
+    width: usize,
+    /// components in each row, keyed by column
+    rows: Vec<HashMap<usize, Component>>,
+}
+
+fn sources(row: &HashMap<usize, Component>) -> impl Iterator<Item = usize> + '_ {
+    row.iter()
+        .filter(|(_, component)| **component == Component::Source)
+        .map(|(col_idx, _)| *col_idx)
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+enum Edges {
+    /// beams leaving the manifold are lost
+    #[default]
+    Absorb,
+    /// beams bounce off the wall back into the edge column
+    Reflect,
+    /// beams leaving one side come back in on the other
+    Wrap,
+}
+
+impl Edges {
+    fn step(self, col_idx: usize, delta: isize, width: usize) -> Option<usize> {
+        let target = col_idx as isize + delta;
+        if (0..width as isize).contains(&target) {
+            return Some(target as usize);
+        }
+
+        match self {
+            Edges::Absorb => None,
+            Edges::Reflect => Some(if target < 0 { 0 } else { width - 1 }),
+            Edges::Wrap => Some(target.rem_euclid(width as isize) as usize),
+        }
+    }
+}
 
+fn parse_input<'a>(input: impl Iterator<Item = &'a str>) -> Manifold {
+    let mut width = 0;
     let rows = input
-        .filter_map(|row| {
-            let row: Vec<usize> = row
-                .chars()
+        .map(|row| {
+            width = width.max(row.chars().count());
+            row.chars()
                 .enumerate()
-                .filter_map(|(i, c)| if c == '^' { Some(i) } else { None })
-                .collect();
-            if !row.is_empty() { Some(row) } else { None }
+                .filter_map(|(i, c)| Component::from_char(c).map(|component| (i, component)))
+                .collect()
         })
         .collect();
 
-    (start_idx, rows)
+    Manifold { width, rows }
 }
 
-fn solve1(input: &(usize, Vec<Vec<usize>>)) -> usize {
+fn solve1(manifold: &Manifold, edges: Edges) -> usize {
     let mut beams = HashSet::new();
-    beams.insert(input.0);
 
     let mut splits = 0;
-    for row in &input.1 {
-        for col_idx in row {
-            if beams.contains(col_idx) {
+    for row in &manifold.rows {
+        let mut next_beams = HashSet::new();
+        for col_idx in beams {
+            let Some(component) = row.get(&col_idx) else {
+                next_beams.insert(col_idx);
+                continue;
+            };
+            if *component == Component::Splitter {
                 splits += 1;
-                beams.remove(col_idx);
-                beams.insert(*col_idx - 1);
-                beams.insert(*col_idx + 1);
+            }
+            for delta in component.deltas() {
+                next_beams.extend(edges.step(col_idx, *delta, manifold.width));
             }
         }
+        next_beams.extend(sources(row));
+        beams = next_beams;
     }
 
     splits
 }
 
-fn solve2(input: &(usize, Vec<Vec<usize>>)) -> usize {
-    fn split(
-        beam: usize,
-        splitters: &[Vec<usize>],
-        memory: &mut HashMap<(usize, usize), usize>,
-    ) -> usize {
-        let mut paths = 1;
-        if let Some(row) = splitters.first() {
-            if row.contains(&beam) {
-                if let Some(memorized_result) = memory.get(&(beam, splitters.len())) {
-                    paths = *memorized_result;
-                } else {
-                    paths = split(beam - 1, &splitters[1..], memory)
-                        + split(beam + 1, &splitters[1..], memory);
-                    memory.insert((beam, splitters.len()), paths);
-                }
-            } else {
-                paths = split(beam, &splitters[1..], memory)
+fn add_timelines(timelines: &mut HashMap<usize, usize>, col_idx: usize, count: usize) {
+    let total = timelines.entry(col_idx).or_insert(0);
+    *total = total
+        .checked_add(count)
+        .expect("timeline count overflows usize");
+}
+
+/// moves every timeline through one row, counts are checked since they can double
+/// on every splitter of a deep manifold
+fn step_row(
+    manifold: &Manifold,
+    row: &HashMap<usize, Component>,
+    timelines: &HashMap<usize, usize>,
+    edges: Edges,
+) -> HashMap<usize, usize> {
+    let mut next_timelines = HashMap::new();
+    for (&col_idx, &count) in timelines {
+        let deltas = row.get(&col_idx).map_or(&[0][..], |c| c.deltas());
+        for delta in deltas {
+            if let Some(target) = edges.step(col_idx, *delta, manifold.width) {
+                add_timelines(&mut next_timelines, target, count);
             }
         }
+    }
+    for col_idx in sources(row) {
+        add_timelines(&mut next_timelines, col_idx, 1);
+    }
+
+    next_timelines
+}
 
-        paths
+/// number of timelines with a beam in each column after passing each row
+fn trace(manifold: &Manifold, edges: Edges) -> Vec<HashMap<usize, usize>> {
+    let mut traced: Vec<HashMap<usize, usize>> = Vec::with_capacity(manifold.rows.len());
+    for row in &manifold.rows {
+        let next_timelines = match traced.last() {
+            Some(timelines) => step_row(manifold, row, timelines, edges),
+            None => step_row(manifold, row, &HashMap::new(), edges),
+        };
+        traced.push(next_timelines);
     }
 
-    let mut beams = HashSet::new();
-    beams.insert(input.0);
+    traced
+}
+
+fn solve2(manifold: &Manifold, edges: Edges) -> usize {
+    // only the timelines leaving the current row are kept while sweeping down
+    manifold
+        .rows
+        .iter()
+        .fold(HashMap::new(), |timelines, row| {
+            step_row(manifold, row, &timelines, edges)
+        })
+        .values()
+        .try_fold(0usize, |total, count| total.checked_add(*count))
+        .expect("timeline count overflows usize")
+}
+
+#[derive(Debug)]
+struct SplitterStats {
+    row_idx: usize,
+    col_idx: usize,
+    /// timelines arriving at the splitter, zero if it is never reached
+    timelines: usize,
+}
+
+#[derive(Debug)]
+struct Report {
+    splitters: Vec<SplitterStats>,
+    /// timelines leaving the bottom edge in each column
+    exits: BTreeMap<usize, usize>,
+}
+
+fn report(manifold: &Manifold, edges: Edges) -> Report {
+    let traced = trace(manifold, edges);
+
+    let mut splitters = Vec::new();
+    for (row_idx, row) in manifold.rows.iter().enumerate() {
+        let mut columns: Vec<usize> = row
+            .iter()
+            .filter(|(_, component)| **component == Component::Splitter)
+            .map(|(col_idx, _)| *col_idx)
+            .collect();
+        columns.sort();
+        for col_idx in columns {
+            let timelines = row_idx
+                .checked_sub(1)
+                .and_then(|above| traced[above].get(&col_idx))
+                .copied()
+                .unwrap_or(0);
+            splitters.push(SplitterStats {
+                row_idx,
+                col_idx,
+                timelines,
+            });
+        }
+    }
+
+    let exits = traced
+        .last()
+        .map(|timelines| timelines.iter().map(|(c, t)| (*c, *t)).collect())
+        .unwrap_or_default();
+
+    Report { splitters, exits }
+}
+
+fn print_report(report: &Report) {
+    let (hit, unreached): (Vec<_>, Vec<_>) = report.splitters.iter().partition(|s| s.timelines > 0);
+
+    println!("Splitters hit: {}", hit.len());
+    for splitter in hit {
+        println!(
+            "  row {} col {}: {} timelines",
+            splitter.row_idx, splitter.col_idx, splitter.timelines
+        );
+    }
+    println!("Splitters never reached: {}", unreached.len());
+    for splitter in unreached {
+        println!("  row {} col {}", splitter.row_idx, splitter.col_idx);
+    }
+    println!("Exits:");
+    for (col_idx, timelines) in &report.exits {
+        println!("  col {col_idx}: {timelines} timelines");
+    }
+}
 
-    let mut memory = HashMap::new();
+/// the manifold with `|` wherever a beam passes through an empty cell
+fn render_beams(manifold: &Manifold, traced: &[HashMap<usize, usize>]) -> String {
+    let mut rendered = Vec::new();
+    for (row, timelines) in manifold.rows.iter().zip(traced) {
+        let line: String = (0..manifold.width)
+            .map(|col_idx| match row.get(&col_idx) {
+                Some(component) => component.symbol(),
+                None if timelines.contains_key(&col_idx) => '|',
+                None => '.',
+            })
+            .collect();
+        rendered.push(line);
+    }
 
-    split(input.0, &input.1, &mut memory)
+    rendered.join("\n")
+}
+
+/// the manifold with the timeline count of every beam cell, one padded column per cell
+fn render_timelines(manifold: &Manifold, traced: &[HashMap<usize, usize>]) -> String {
+    let cell_width = traced
+        .iter()
+        .flat_map(|timelines| timelines.values())
+        .max()
+        .map_or(1, |max| max.to_string().len());
+
+    let mut rendered = Vec::new();
+    for (row, timelines) in manifold.rows.iter().zip(traced) {
+        let cells: Vec<String> = (0..manifold.width)
+            .map(
+                |col_idx| match (row.get(&col_idx), timelines.get(&col_idx)) {
+                    (Some(component), _) => format!("{:>cell_width$}", component.symbol()),
+                    (None, Some(count)) => format!("{count:>cell_width$}"),
+                    (None, None) => format!("{:>cell_width$}", '.'),
+                },
+            )
+            .collect();
+        rendered.push(cells.join(" "));
+    }
+
+    rendered.join("\n")
+}
+
+/// svg heat-map of the timeline counts on a log scale, blue for few and red for many
+fn render_svg(manifold: &Manifold, traced: &[HashMap<usize, usize>]) -> String {
+    const CELL: usize = 10;
+
+    let max = traced
+        .iter()
+        .flat_map(|timelines| timelines.values())
+        .max()
+        .copied()
+        .unwrap_or(1);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        manifold.width * CELL,
+        manifold.rows.len() * CELL
+    );
+    svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"black\"/>\n");
+
+    for (row_idx, (row, timelines)) in manifold.rows.iter().zip(traced).enumerate() {
+        let y = row_idx * CELL;
+        let mut columns: Vec<_> = timelines.iter().collect();
+        columns.sort();
+        for (col_idx, count) in columns {
+            let t = if max > 1 {
+                (*count as f64).ln() / (max as f64).ln()
+            } else {
+                1.0
+            };
+            let red = (255.0 * t) as u8;
+            let blue = (255.0 * (1.0 - t)) as u8;
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{y}\" width=\"{CELL}\" height=\"{CELL}\" fill=\"rgb({red},0,{blue})\"><title>{count}</title></rect>\n",
+                col_idx * CELL
+            ));
+        }
+
+        let mut components: Vec<_> = row.iter().collect();
+        components.sort_by_key(|(col_idx, _)| **col_idx);
+        for (col_idx, component) in components {
+            svg.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"{CELL}\" font-family=\"monospace\" text-anchor=\"middle\" fill=\"white\">{}</text>\n",
+                col_idx * CELL + CELL / 2,
+                y + CELL - 1,
+                component.symbol()
+            ));
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
 }
 
 fn main() {
+    let edges = std::env::args()
+        .skip(1)
+        .find_map(|arg| {
+            arg.strip_prefix("--edges=").map(|edges| match edges {
+                "absorb" => Edges::Absorb,
+                "reflect" => Edges::Reflect,
+                "wrap" => Edges::Wrap,
+                _ => panic!("Invalid edge behaviour {edges}"),
+            })
+        })
+        .unwrap_or_default();
+
     let parsed = parse_input(include_str!("input.txt").lines());
 
-    println!("Part 1: {}", solve1(&parsed));
-    println!("Part 2: {}", solve2(&parsed));
+    println!("Part 1: {}", solve1(&parsed, edges));
+    println!("Part 2: {}", solve2(&parsed, edges));
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--beams" => println!("{}", render_beams(&parsed, &trace(&parsed, edges))),
+            "--timelines" => println!("{}", render_timelines(&parsed, &trace(&parsed, edges))),
+            "--svg" => print!("{}", render_svg(&parsed, &trace(&parsed, edges))),
+            "--report" => print_report(&report(&parsed, edges)),
+            _ => {}
+        }
+    }
 }
 
 #[cfg(test)]
@@ -107,12 +403,131 @@ mod tests {
     #[test]
     fn test1() {
         let parsed = parse_input(INPUT.iter().cloned());
-        assert_eq!(solve1(&parsed), 21);
+        assert_eq!(solve1(&parsed, Edges::default()), 21);
     }
 
     #[test]
     fn test2() {
         let parsed = parse_input(INPUT.iter().cloned());
-        assert_eq!(solve2(&parsed), 40);
+        assert_eq!(solve2(&parsed, Edges::default()), 40);
+    }
+
+    #[test]
+    fn test2_deep() {
+        // deep enough to overflow the stack when recursing once per row
+        let mut input = vec!["..S..", "..^.."];
+        input.extend(std::iter::repeat_n("^...^", 100_000));
+        let parsed = parse_input(input.into_iter());
+        assert_eq!(solve1(&parsed, Edges::default()), 1);
+        assert_eq!(solve2(&parsed, Edges::default()), 2);
+    }
+
+    #[test]
+    fn test_width() {
+        let parsed = parse_input(INPUT.iter().cloned());
+        assert_eq!(parsed.width, 15);
+        assert_eq!(parsed.rows.len(), 16);
+        assert_eq!(parsed.rows[0].get(&7), Some(&Component::Source));
+    }
+
+    #[test]
+    fn test_edges() {
+        // the first splitter sits in column 0 and sends a beam off the left edge
+        let input = ["S..", "^..", "..^", "^.."];
+        let parsed = parse_input(input.into_iter());
+
+        assert_eq!(solve1(&parsed, Edges::Absorb), 1);
+        assert_eq!(solve2(&parsed, Edges::Absorb), 1);
+
+        // the lost beam bounces back into column 0 and is split again by the last row
+        assert_eq!(solve1(&parsed, Edges::Reflect), 2);
+        assert_eq!(solve2(&parsed, Edges::Reflect), 3);
+
+        // the lost beam wraps to column 2, where it is split back around to column 0
+        assert_eq!(solve1(&parsed, Edges::Wrap), 3);
+        assert_eq!(solve2(&parsed, Edges::Wrap), 4);
+    }
+
+    #[test]
+    fn test_components() {
+        let input = [
+            ".S...S.",  // two sources
+            ".^.../.",  // split one beam, deflect the other left
+            "#..S...",  // absorb the left beam, add a third source
+            "..\\.#..", // deflect a beam right into column 3, absorb column 4
+            "...^...",  // split the merged beams
+        ];
+        let parsed = parse_input(input.into_iter());
+
+        assert_eq!(solve1(&parsed, Edges::default()), 2);
+        // two timelines reach the final splitter together and are each split
+        assert_eq!(solve2(&parsed, Edges::default()), 4);
+    }
+
+    #[test]
+    fn test_render_beams() {
+        let parsed = parse_input(INPUT.iter().cloned());
+        let rendered = render_beams(&parsed, &trace(&parsed, Edges::default()));
+        let expected = [
+            ".......S.......",
+            ".......|.......",
+            "......|^|......",
+            "......|.|......",
+            ".....|^|^|.....",
+            ".....|.|.|.....",
+            "....|^|^|^|....",
+            "....|.|.|.|....",
+            "...|^|^|||^|...",
+            "...|.|.|||.|...",
+            "..|^|^|||^|^|..",
+            "..|.|.|||.|.|..",
+            ".|^|||^||.||^|.",
+            ".|.|||.||.||.|.",
+            "|^|^|^|^|^|||^|",
+            "|.|.|.|.|.|||.|",
+        ];
+        assert_eq!(rendered, expected.join("\n"));
+    }
+
+    #[test]
+    fn test_render_timelines() {
+        let parsed = parse_input([".S.", ".^.", "..."].into_iter());
+        let rendered = render_timelines(&parsed, &trace(&parsed, Edges::default()));
+        assert_eq!(rendered, ". S .\n1 ^ 1\n1 . 1");
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let parsed = parse_input(INPUT.iter().cloned());
+        let svg = render_svg(&parsed, &trace(&parsed, Edges::default()));
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+        // a background rect and one per beam cell, a text for the source and each splitter
+        assert_eq!(svg.matches("<rect").count(), 1 + 82);
+        assert_eq!(svg.matches("<text").count(), 1 + 22);
+    }
+
+    #[test]
+    fn test_report() {
+        let parsed = parse_input(INPUT.iter().cloned());
+        let report = report(&parsed, Edges::default());
+
+        let hit = report.splitters.iter().filter(|s| s.timelines > 0).count();
+        assert_eq!(hit, solve1(&parsed, Edges::default()));
+
+        let unreached: Vec<_> = report
+            .splitters
+            .iter()
+            .filter(|s| s.timelines == 0)
+            .map(|s| (s.row_idx, s.col_idx))
+            .collect();
+        assert_eq!(unreached, vec![(14, 9)]);
+
+        let first = &report.splitters[0];
+        assert_eq!((first.row_idx, first.col_idx, first.timelines), (2, 7, 1));
+
+        assert_eq!(report.exits.values().sum::<usize>(), 40);
+        assert_eq!(report.exits.get(&0), Some(&1));
+        assert_eq!(report.exits.get(&1), None);
     }
 }
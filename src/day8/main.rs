@@ -1,5 +1,3 @@
-use std::collections::HashSet;
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Location {
     x: i64,
@@ -8,9 +6,9 @@ struct Location {
 }
 
 impl Location {
-    fn distance(&self, other: &Self) -> f64 {
-        (((self.x - other.x).pow(2) + (self.y - other.y).pow(2) + (self.z - other.z).pow(2)) as f64)
-            .sqrt()
+    /// squared straight line distance, which orders pairs the same as the distance itself
+    fn distance_squared(&self, other: &Self) -> i64 {
+        (self.x - other.x).pow(2) + (self.y - other.y).pow(2) + (self.z - other.z).pow(2)
     }
 }
 
@@ -27,42 +25,88 @@ fn parse_input<'a>(input: impl Iterator<Item = &'a str>) -> Vec<Location> {
         .collect()
 }
 
-fn solve1(input: &[Location], num_pairs: usize) -> usize {
-    let mut circuits: Vec<HashSet<&Location>> =
-        input.iter().map(|l| HashSet::from_iter([l])).collect();
+/// disjoint-set union over box indices, with path compression and union by size
+struct Circuits {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    count: usize,
+}
 
-    let mut box_pairs = Vec::new();
-    for (a_idx, box_a) in input.iter().enumerate() {
-        for box_b in input.iter().skip(a_idx + 1) {
-            let dist = box_a.distance(box_b);
-            box_pairs.push((dist, box_a, box_b));
+impl Circuits {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+            count: len,
+        }
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        let mut root = idx;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        while self.parent[idx] != root {
+            let next = self.parent[idx];
+            self.parent[idx] = root;
+            idx = next;
+        }
+
+        root
+    }
+
+    /// connect the circuits of both boxes, returns false if they were already connected
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
         }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.count -= 1;
+
+        true
     }
 
-    box_pairs.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-
-    for (_, box_a, box_b) in box_pairs.into_iter().take(num_pairs) {
-        let circuit_a_idx = circuits
-            .iter()
-            .enumerate()
-            .find_map(|c| if c.1.contains(box_a) { Some(c.0) } else { None })
-            .unwrap();
-
-        let circuit_b_idx = circuits
-            .iter()
-            .enumerate()
-            .find_map(|c| if c.1.contains(box_b) { Some(c.0) } else { None })
-            .unwrap();
-
-        if circuit_a_idx != circuit_b_idx {
-            // connect them
-            let circuit_b = circuits[circuit_b_idx].clone();
-            circuits[circuit_a_idx].extend(&circuit_b);
-            circuits.remove(circuit_b_idx);
+    fn sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.parent.len())
+            .filter(|idx| self.parent[*idx] == *idx)
+            .map(|idx| self.size[idx])
+    }
+}
+
+/// every pair of boxes as (squared distance, a index, b index)
+fn box_pairs(input: &[Location]) -> Vec<(i64, u32, u32)> {
+    let mut box_pairs = Vec::with_capacity(input.len() * input.len().saturating_sub(1) / 2);
+    for (a_idx, box_a) in input.iter().enumerate() {
+        for (b_idx, box_b) in input.iter().enumerate().skip(a_idx + 1) {
+            box_pairs.push((box_a.distance_squared(box_b), a_idx as u32, b_idx as u32));
         }
     }
 
-    let mut circuit_lengths: Vec<usize> = circuits.iter().map(|c| c.len()).collect();
+    box_pairs
+}
+
+fn solve1(input: &[Location], num_pairs: usize) -> usize {
+    let mut box_pairs = box_pairs(input);
+
+    // only the closest pairs are connected, so there is no need to sort the rest
+    if num_pairs < box_pairs.len() {
+        box_pairs.select_nth_unstable(num_pairs);
+        box_pairs.truncate(num_pairs);
+    }
+
+    let mut circuits = Circuits::new(input.len());
+    for (_, a_idx, b_idx) in box_pairs {
+        circuits.union(a_idx as usize, b_idx as usize);
+    }
+
+    let mut circuit_lengths: Vec<usize> = circuits.sizes().collect();
     circuit_lengths.sort();
     circuit_lengths.reverse();
 
@@ -70,41 +114,13 @@ fn solve1(input: &[Location], num_pairs: usize) -> usize {
 }
 
 fn solve2(input: &[Location]) -> i64 {
-    let mut circuits: Vec<HashSet<&Location>> =
-        input.iter().map(|l| HashSet::from_iter([l])).collect();
+    let mut box_pairs = box_pairs(input);
+    box_pairs.sort_unstable();
 
-    let mut box_pairs = Vec::new();
-    for (a_idx, box_a) in input.iter().enumerate() {
-        for box_b in input.iter().skip(a_idx + 1) {
-            let dist = box_a.distance(box_b);
-            box_pairs.push((dist, box_a, box_b));
-        }
-    }
-
-    box_pairs.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-
-    for (_, box_a, box_b) in box_pairs.into_iter() {
-        let circuit_a_idx = circuits
-            .iter()
-            .enumerate()
-            .find_map(|c| if c.1.contains(box_a) { Some(c.0) } else { None })
-            .unwrap();
-
-        let circuit_b_idx = circuits
-            .iter()
-            .enumerate()
-            .find_map(|c| if c.1.contains(box_b) { Some(c.0) } else { None })
-            .unwrap();
-
-        if circuit_a_idx != circuit_b_idx {
-            // connect them
-            let circuit_b = circuits[circuit_b_idx].clone();
-            circuits[circuit_a_idx].extend(&circuit_b);
-            circuits.remove(circuit_b_idx);
-
-            if circuits.len() == 1 {
-                return box_a.x * box_b.x;
-            }
+    let mut circuits = Circuits::new(input.len());
+    for (_, a_idx, b_idx) in box_pairs {
+        if circuits.union(a_idx as usize, b_idx as usize) && circuits.count == 1 {
+            return input[a_idx as usize].x * input[b_idx as usize].x;
         }
     }
 
@@ -157,4 +173,33 @@ mod tests {
         let parsed = parse_input(INPUT.iter().cloned());
         assert_eq!(solve2(&parsed), 25272);
     }
+
+    #[test]
+    fn test_circuits() {
+        let mut circuits = Circuits::new(5);
+        assert!(circuits.union(0, 1));
+        assert!(circuits.union(2, 3));
+        assert!(circuits.union(1, 3));
+        assert!(!circuits.union(0, 2));
+        assert_eq!(circuits.count, 2);
+        assert_eq!(circuits.find(0), circuits.find(3));
+
+        let mut sizes: Vec<usize> = circuits.sizes().collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 4]);
+    }
+
+    #[test]
+    fn test_large() {
+        // boxes along a line with growing gaps, the third closest pair is already connected
+        let input: Vec<Location> = (0..2_000)
+            .map(|i| Location {
+                x: i * i,
+                y: 0,
+                z: 0,
+            })
+            .collect();
+        assert_eq!(solve1(&input, 3), 3);
+        assert_eq!(solve2(&input), 1998 * 1998 * 1999 * 1999);
+    }
 }